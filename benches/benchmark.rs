@@ -122,6 +122,76 @@ fn bench_ua(c: &mut Criterion) {
     });
 }
 
+fn bench_literal_prefilter(c: &mut Criterion) {
+    let file = File::open("./src/core/tests/test_ua.yaml").unwrap();
+    let test_cases: TestCases = serde_yaml::from_reader(file).unwrap();
+
+    let parser = UserAgentParser::builder()
+        .build_from_yaml("./src/core/regexes.yaml")
+        .expect("Parser creation failed");
+
+    c.bench_function("parse", |b| {
+        b.iter(|| {
+            for case in &test_cases.test_cases {
+                black_box(parser.parse(&case.user_agent_string));
+            }
+        })
+    });
+
+    let parser = UserAgentParser::builder()
+        .with_literal_prefilter(true)
+        .build_from_yaml("./src/core/regexes.yaml")
+        .expect("Parser creation failed");
+
+    c.bench_function("parse literal prefilter", |b| {
+        b.iter(|| {
+            for case in &test_cases.test_cases {
+                black_box(parser.parse(&case.user_agent_string));
+            }
+        })
+    });
+
+    let parser = UserAgentParser::builder()
+        .with_regex_set_prefilter(false)
+        .with_literal_prefilter(true)
+        .build_from_yaml("./src/core/regexes.yaml")
+        .expect("Parser creation failed");
+
+    c.bench_function("parse literal prefilter without regex set", |b| {
+        b.iter(|| {
+            for case in &test_cases.test_cases {
+                black_box(parser.parse(&case.user_agent_string));
+            }
+        })
+    });
+}
+
+fn bench_batch(c: &mut Criterion) {
+    let file = File::open("./src/core/tests/test_ua.yaml").unwrap();
+    let test_cases: TestCases = serde_yaml::from_reader(file).unwrap();
+
+    let parser = UserAgentParser::builder()
+        .build_from_yaml("./src/core/regexes.yaml")
+        .expect("Parser creation failed");
+
+    // Repeat every string so the batch has the duplicates a real log would.
+    let user_agents: Vec<&str> = test_cases
+        .test_cases
+        .iter()
+        .chain(&test_cases.test_cases)
+        .map(|case| case.user_agent_string.as_str())
+        .collect();
+
+    c.bench_function("parse_batch", |b| {
+        b.iter(|| black_box(parser.parse_batch(&user_agents)))
+    });
+
+    #[cfg(feature = "rayon")]
+    c.bench_function("par_parse", |b| {
+        b.iter(|| black_box(parser.par_parse(&user_agents)))
+    });
+}
+
 criterion_group!(
     name = benches;
     config = Criterion::default()
@@ -137,6 +207,6 @@ criterion_group!(
         .nresamples(10_000)
         // total samples to collect within the set measurement time
         .sample_size(100);
-    targets = bench_device, bench_os, bench_ua
+    targets = bench_device, bench_os, bench_ua, bench_literal_prefilter, bench_batch
 );
 criterion_main!(benches);
@@ -0,0 +1,40 @@
+use std::{env, fs, path::Path};
+
+/// When the `embedded-regexes` feature is enabled, `regexes.yaml` from the
+/// uap-core submodule is compiled into the crate. This records which uap-core
+/// release it came from so it can be reported at runtime.
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+
+    if env::var_os("CARGO_FEATURE_EMBEDDED_REGEXES").is_none() {
+        return;
+    }
+
+    let core = Path::new("src/core");
+    let regexes = core.join("regexes.yaml");
+    let package = core.join("package.json");
+    println!("cargo:rerun-if-changed={}", regexes.display());
+    println!("cargo:rerun-if-changed={}", package.display());
+
+    assert!(
+        regexes.exists(),
+        "the `embedded-regexes` feature needs {}, run `git submodule update --init`",
+        regexes.display()
+    );
+
+    let version = fs::read_to_string(&package)
+        .ok()
+        .and_then(|json| package_version(&json))
+        .unwrap_or_else(|| String::from("unknown"));
+
+    println!("cargo:rustc-env=UAP_CORE_VERSION={version}");
+}
+
+/// Pulls the top level `"version"` field out of uap-core's `package.json`
+/// without needing a JSON parser at build time.
+fn package_version(json: &str) -> Option<String> {
+    let rest = &json[json.find("\"version\"")? + "\"version\"".len()..];
+    let rest = rest.trim_start().strip_prefix(':')?.trim_start();
+    let rest = rest.strip_prefix('"')?;
+    Some(rest[..rest.find('"')?].to_owned())
+}
@@ -0,0 +1,366 @@
+//! Reads user agent strings out of web server access logs and tallies what
+//! the parser makes of them, so a log file can be summarized without first
+//! extracting the strings with other tools. Requires the `access-log`
+//! feature.
+//!
+//! Logs are read line by line into a `UsageStats`, so memory use depends on
+//! the number of distinct browsers, operating systems and devices rather than
+//! on the size of the log. Wrapping the parser in a `CachingParser` avoids
+//! parsing the same string over and over.
+//!
+//! ```rust
+//! # use uaparser::{access_log::{LogFormat, LogSummary}, *};
+//! let regexes = br"
+//! user_agent_parsers:
+//!   - regex: '(Firefox)/(\d+)\.(\d+)'
+//! os_parsers: []
+//! device_parsers: []
+//! ";
+//! let parser = UserAgentParser::from_bytes(regexes).unwrap();
+//! let log = br#"127.0.0.1 - - [10/Oct/2023:13:55:36 +0000] "GET / HTTP/1.1" 200 2326 "-" "Mozilla/5.0 Firefox/115.0"
+//! 127.0.0.1 - - [10/Oct/2023:13:55:37 +0000] "GET /favicon.ico HTTP/1.1" 404 0 "-" "-"
+//! "#;
+//!
+//! let mut summary = LogSummary::default();
+//! summary.ingest(&parser, &log[..], &LogFormat::Combined).unwrap();
+//! assert_eq!((summary.lines, summary.missing), (2, 1));
+//! assert_eq!(summary.stats.count(Dimension::BrowserVersion, "Firefox 115"), 1);
+//! ```
+
+use std::{
+    borrow::Cow,
+    io::{self, BufRead},
+};
+
+use super::{Parser, Serialize, UsageStats};
+
+/// The layout of the lines of an access log.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum LogFormat {
+    /// The NCSA combined format written by Apache and nginx by default, where
+    /// the user agent is the third quoted field. Lines in the common format,
+    /// which has no such field, are counted as missing a user agent.
+    Combined,
+    /// The W3C extended format written by IIS, where the `#Fields` directive
+    /// names the `cs(User-Agent)` column.
+    W3c,
+    /// One JSON object per line, with the user agent in the named field.
+    /// Nested fields are separated by dots, as in `request.user_agent`.
+    JsonLines(String),
+}
+
+/// The clients seen in one or more access logs, along with counts of the
+/// lines they could not be read from.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct LogSummary {
+    /// The number of log lines read, not counting blank lines and directives
+    pub lines: u64,
+    /// Lines without a user agent, or with `-` in its place
+    pub missing: u64,
+    /// Lines that are not in the expected format
+    pub malformed: u64,
+    /// The clients parsed from every line with a user agent
+    pub stats: UsageStats,
+}
+
+impl LogSummary {
+    /// Reads `reader` to the end, adding every user agent found in it.
+    pub fn ingest<P: Parser + ?Sized>(
+        &mut self,
+        parser: &P,
+        mut reader: impl BufRead,
+        format: &LogFormat,
+    ) -> io::Result<()> {
+        let mut extractor = Extractor::new(format);
+        let mut buffer = Vec::new();
+
+        loop {
+            buffer.clear();
+            if reader.read_until(b'\n', &mut buffer)? == 0 {
+                return Ok(());
+            }
+
+            // Logs are not always valid UTF-8, and a stray byte should not
+            // stop the whole file from being read.
+            let line = String::from_utf8_lossy(&buffer);
+            let line = line.trim_end_matches(['\n', '\r']);
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            match extractor.user_agent(line) {
+                Extracted::UserAgent(user_agent) => {
+                    self.lines += 1;
+                    self.stats.add(&parser.parse(&user_agent));
+                }
+                Extracted::Missing => {
+                    self.lines += 1;
+                    self.missing += 1;
+                }
+                Extracted::Malformed => {
+                    self.lines += 1;
+                    self.malformed += 1;
+                }
+                Extracted::Directive => {}
+            }
+        }
+    }
+
+    /// Adds the counts of `other`, such as the summary of another log file.
+    pub fn merge(&mut self, other: LogSummary) {
+        self.lines += other.lines;
+        self.missing += other.missing;
+        self.malformed += other.malformed;
+        self.stats.merge(other.stats);
+    }
+}
+
+/// What `Extractor::user_agent` found on a line.
+#[derive(Debug, PartialEq)]
+enum Extracted<'a> {
+    UserAgent(Cow<'a, str>),
+    Missing,
+    Malformed,
+    /// A W3C directive such as `#Fields`, which is not a request
+    Directive,
+}
+
+/// Pulls the user agent out of log lines, keeping track of the columns
+/// declared by W3C `#Fields` directives.
+struct Extractor<'f> {
+    format: &'f LogFormat,
+    /// Whether a `#Fields` directive was read yet
+    w3c_fields: bool,
+    /// The `cs(User-Agent)` column of the last `#Fields` directive
+    w3c_column: Option<usize>,
+}
+
+impl<'f> Extractor<'f> {
+    fn new(format: &'f LogFormat) -> Self {
+        Extractor {
+            format,
+            w3c_fields: false,
+            w3c_column: None,
+        }
+    }
+
+    fn user_agent<'a>(&mut self, line: &'a str) -> Extracted<'a> {
+        match self.format {
+            LogFormat::Combined => combined(line),
+            LogFormat::W3c => self.w3c(line),
+            LogFormat::JsonLines(field) => json(line, field),
+        }
+    }
+
+    fn w3c<'a>(&mut self, line: &'a str) -> Extracted<'a> {
+        if let Some(directive) = line.strip_prefix('#') {
+            if let Some(fields) = directive.strip_prefix("Fields:") {
+                self.w3c_fields = true;
+                self.w3c_column = fields
+                    .split_whitespace()
+                    .position(|f| f.eq_ignore_ascii_case("cs(User-Agent)"));
+            }
+            return Extracted::Directive;
+        }
+
+        if !self.w3c_fields {
+            return Extracted::Malformed;
+        }
+        let Some(column) = self.w3c_column else {
+            return Extracted::Missing;
+        };
+        match line.split_whitespace().nth(column) {
+            // IIS writes the spaces of a user agent as `+`.
+            Some(value) => present(Cow::Owned(value.replace('+', " "))),
+            None => Extracted::Malformed,
+        }
+    }
+}
+
+/// The user agent is the third quoted field, after the request line and the
+/// referer. Quotes inside a field are escaped with a backslash.
+fn combined(line: &str) -> Extracted<'_> {
+    let mut fields = 0;
+    let mut rest = line;
+
+    while let Some(start) = rest.find('"') {
+        let field = &rest[start + 1..];
+        let mut escaped = false;
+        let Some(end) = field.char_indices().find_map(|(i, c)| {
+            let end = c == '"' && !escaped;
+            escaped = c == '\\' && !escaped;
+            end.then_some(i)
+        }) else {
+            return Extracted::Malformed;
+        };
+
+        fields += 1;
+        if fields == 3 {
+            let value = &field[..end];
+            return present(if value.contains('\\') {
+                Cow::Owned(value.replace("\\\"", "\"").replace("\\\\", "\\"))
+            } else {
+                Cow::Borrowed(value)
+            });
+        }
+        rest = &field[end + 1..];
+    }
+
+    if fields == 0 {
+        Extracted::Malformed
+    } else {
+        Extracted::Missing
+    }
+}
+
+fn json<'a>(line: &str, field: &str) -> Extracted<'a> {
+    let Ok(value) = serde_json::from_str::<serde_json::Value>(line) else {
+        return Extracted::Malformed;
+    };
+
+    match field
+        .split('.')
+        .try_fold(&value, |value, key| value.get(key))
+        .and_then(serde_json::Value::as_str)
+    {
+        Some(user_agent) => present(Cow::Owned(user_agent.to_owned())),
+        None => Extracted::Missing,
+    }
+}
+
+/// Web servers log a missing header as `-`.
+fn present(user_agent: Cow<'_, str>) -> Extracted<'_> {
+    if user_agent.is_empty() || user_agent == "-" {
+        Extracted::Missing
+    } else {
+        Extracted::UserAgent(user_agent)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Dimension, UserAgentParser};
+
+    fn extract<'a>(format: &LogFormat, lines: &[&'a str]) -> Vec<Extracted<'a>> {
+        let mut extractor = Extractor::new(format);
+        lines
+            .iter()
+            .map(|line| extractor.user_agent(line))
+            .collect()
+    }
+
+    #[test]
+    fn extracts_from_combined_format() {
+        assert_eq!(
+            extract(
+                &LogFormat::Combined,
+                &[
+                    r#"1.2.3.4 - - [10/Oct/2023:13:55:36 +0000] "GET / HTTP/1.1" 200 5 "-" "curl/8.0""#,
+                    r#"1.2.3.4 - - [10/Oct/2023:13:55:36 +0000] "GET /?q=\"x\" HTTP/1.1" 200 5 "https://example.com/" "Agent \"quoted\"" "10.0.0.1""#,
+                    r#"1.2.3.4 - - [10/Oct/2023:13:55:36 +0000] "GET / HTTP/1.1" 200 5"#,
+                    r#"1.2.3.4 - - [10/Oct/2023:13:55:36 +0000] "GET / HTTP/1.1" 200 5 "-" "-""#,
+                    r#"1.2.3.4 - - [10/Oct/2023:13:55:36 +0000] "GET / HTTP/1.1"#,
+                    "not a log line",
+                ]
+            ),
+            vec![
+                Extracted::UserAgent(Cow::Borrowed("curl/8.0")),
+                Extracted::UserAgent(Cow::Borrowed(r#"Agent "quoted""#)),
+                Extracted::Missing,
+                Extracted::Missing,
+                Extracted::Malformed,
+                Extracted::Malformed,
+            ]
+        );
+    }
+
+    #[test]
+    fn extracts_from_w3c_format() {
+        assert_eq!(
+            extract(
+                &LogFormat::W3c,
+                &[
+                    "2023-10-10 13:55:36 GET / 200",
+                    "#Software: Microsoft Internet Information Services 10.0",
+                    "#Fields: date time cs-method cs-uri-stem cs(User-Agent) sc-status",
+                    "2023-10-10 13:55:36 GET / Mozilla/5.0+(Windows+NT+10.0) 200",
+                    "2023-10-10 13:55:36 GET / - 200",
+                    "2023-10-10 13:55:36 GET",
+                    "#Fields: date time cs-method",
+                    "2023-10-10 13:55:36 GET",
+                ]
+            ),
+            vec![
+                Extracted::Malformed,
+                Extracted::Directive,
+                Extracted::Directive,
+                Extracted::UserAgent(Cow::Borrowed("Mozilla/5.0 (Windows NT 10.0)")),
+                Extracted::Missing,
+                Extracted::Malformed,
+                Extracted::Directive,
+                Extracted::Missing,
+            ]
+        );
+    }
+
+    #[test]
+    fn extracts_from_json_lines() {
+        assert_eq!(
+            extract(
+                &LogFormat::JsonLines(String::from("request.user_agent")),
+                &[
+                    r#"{"request": {"user_agent": "curl/8.0"}}"#,
+                    r#"{"request": {"path": "/"}}"#,
+                    r#"{"request": "#,
+                ]
+            ),
+            vec![
+                Extracted::UserAgent(Cow::Borrowed("curl/8.0")),
+                Extracted::Missing,
+                Extracted::Malformed,
+            ]
+        );
+    }
+
+    #[test]
+    fn summarizes_logs() {
+        let parser = UserAgentParser::from_bytes(
+            br"
+user_agent_parsers:
+  - regex: '(Firefox)/(\d+)\.(\d+)'
+os_parsers:
+  - regex: '(Windows) NT (\d+)\.(\d+)'
+device_parsers: []
+",
+        )
+        .expect("Parser creation failed");
+        let log = "\
+{\"ua\": \"Mozilla/5.0 (Windows NT 10.0) Firefox/115.0\"}
+
+{\"ua\": \"Mozilla/5.0 (Windows NT 10.0) Firefox/115.0\"}
+{\"ua\": \"curl/8.0\"}
+{\"ua\": null}
+";
+
+        let format = LogFormat::JsonLines(String::from("ua"));
+        let mut summary = LogSummary::default();
+        summary.ingest(&parser, log.as_bytes(), &format).unwrap();
+        let mut other = LogSummary::default();
+        other.ingest(&parser, &b"oops\n"[..], &format).unwrap();
+        summary.merge(other);
+
+        assert_eq!(
+            (summary.lines, summary.missing, summary.malformed),
+            (5, 1, 1)
+        );
+        assert_eq!(summary.stats.total(), 3);
+        assert_eq!(
+            summary
+                .stats
+                .count(Dimension::BrowserVersion, "Firefox 115"),
+            2
+        );
+        assert_eq!(summary.stats.count(Dimension::OsVersion, "Windows 10"), 2);
+    }
+}
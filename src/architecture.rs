@@ -0,0 +1,35 @@
+use std::borrow::Cow;
+
+use super::{Deserialize, Serialize};
+
+/// Describes the CPU `Family` a client runs on, normalized to names such as
+/// `x86`, `x86_64`, `arm` and `arm64`, and whether the agent is `Emulated`
+#[derive(Clone, Debug, Deserialize, Serialize, Eq, Hash, PartialEq)]
+pub struct Architecture<'a> {
+    pub family: Cow<'a, str>,
+    /// Set when a 32-bit agent runs on a 64-bit OS, as marked by `WOW64` or
+    /// `i686 on x86_64`. The `family` is that of the OS, so a 64-bit build
+    /// would also run.
+    pub emulated: bool,
+}
+
+impl Default for Architecture<'_> {
+    fn default() -> Self {
+        Self {
+            family: Cow::Borrowed("Other"),
+            emulated: false,
+        }
+    }
+}
+
+impl Architecture<'_> {
+    /// Converts this into an `Architecture` that owns all of its data, so it
+    /// can outlive the user agent string it was parsed from.
+    #[must_use]
+    pub fn into_owned(self) -> Architecture<'static> {
+        Architecture {
+            family: Cow::Owned(self.family.into_owned()),
+            emulated: self.emulated,
+        }
+    }
+}
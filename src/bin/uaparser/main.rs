@@ -0,0 +1,284 @@
+//! Parses user agent strings from the command line, stdin or files and
+//! prints what the parser made of them.
+//!
+//! ```text
+//! uaparser --regexes regexes.yaml "Mozilla/5.0 (X11; Linux x86_64) Firefox/115.0"
+//! cut -f3 agents.tsv | uaparser --regexes regexes.yaml --format csv
+//! uaparser logs --regexes regexes.yaml --top 20 /var/log/nginx/access.log
+//! ```
+
+use std::{
+    error::Error,
+    fs::File,
+    io::{self, BufRead, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use clap::Parser as _;
+use uaparser::{
+    access_log::{LogFormat, LogSummary},
+    CachingParser, Parser, UserAgentParser, UserAgentParserBuilder,
+};
+
+mod output;
+use self::output::{write_summary, Format, Output};
+
+#[derive(clap::Parser)]
+#[command(
+    name = "uaparser",
+    version,
+    about,
+    args_conflicts_with_subcommands = true
+)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    #[command(flatten)]
+    parse: ParseArgs,
+
+    #[command(flatten)]
+    parser: ParserArgs,
+}
+
+#[derive(clap::Subcommand)]
+enum Command {
+    /// Summarizes the browsers, operating systems and devices in access logs
+    Logs(LogsArgs),
+}
+
+#[derive(clap::Args)]
+struct ParseArgs {
+    /// User agent strings to parse. Without any, strings are read from
+    /// `--file` or, failing that, one per line from stdin.
+    user_agents: Vec<String>,
+
+    /// Reads user agent strings from a file, one per line. May be repeated;
+    /// `-` reads stdin.
+    #[arg(short, long = "file", value_name = "PATH")]
+    files: Vec<PathBuf>,
+
+    #[arg(long, value_enum, default_value_t = Format::Table)]
+    format: Format,
+}
+
+#[derive(clap::Args)]
+struct LogsArgs {
+    /// Access logs to read. Without any, the log is read from stdin.
+    files: Vec<PathBuf>,
+
+    #[arg(long, value_enum, default_value_t = LogFormatArg::Combined)]
+    log_format: LogFormatArg,
+
+    /// The field holding the user agent in `jsonl` logs, with nested fields
+    /// separated by dots
+    #[arg(long, default_value = "user_agent")]
+    field: String,
+
+    /// How many entries to list per table
+    #[arg(long, default_value_t = 10)]
+    top: usize,
+
+    /// How many parsed user agents to keep cached
+    #[arg(long, default_value_t = 10_000)]
+    cache_size: usize,
+
+    #[arg(long, value_enum, default_value_t = Format::Table)]
+    format: Format,
+}
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+enum LogFormatArg {
+    /// Apache and nginx combined log format
+    Combined,
+    /// W3C extended log format, as written by IIS
+    W3c,
+    /// One JSON object per line, see `--field`
+    Jsonl,
+}
+
+/// Mirrors the switches of `UserAgentParserBuilder`.
+#[derive(clap::Args)]
+#[allow(clippy::struct_excessive_bools)]
+struct ParserArgs {
+    /// The `regexes.yaml` to build the parser from. Defaults to the embedded
+    /// one when built with the `embedded-regexes` feature.
+    #[arg(short, long, value_name = "PATH", global = true)]
+    regexes: Option<String>,
+
+    /// Skips device parsing
+    #[arg(long, global = true)]
+    no_device: bool,
+
+    /// Skips OS parsing
+    #[arg(long, global = true)]
+    no_os: bool,
+
+    /// Skips user agent parsing
+    #[arg(long, global = true)]
+    no_user_agent: bool,
+
+    /// Detects the browser engine
+    #[arg(long, global = true)]
+    engine: bool,
+
+    /// Detects the CPU architecture
+    #[arg(long, global = true)]
+    architecture: bool,
+
+    /// Classifies bots, crawlers and HTTP libraries
+    #[arg(long, global = true)]
+    bot: bool,
+
+    /// Compiles the regexes without unicode support
+    #[arg(long, global = true)]
+    no_unicode: bool,
+
+    /// Enables the literal prefilter
+    #[arg(long, global = true)]
+    literal_prefilter: bool,
+
+    /// Leaves out rules that fail to compile instead of refusing to start,
+    /// listing them on stderr
+    #[arg(long, global = true)]
+    lenient: bool,
+}
+
+impl ParserArgs {
+    fn builder(&self) -> UserAgentParserBuilder {
+        UserAgentParser::builder()
+            .with_device(!self.no_device)
+            .with_os(!self.no_os)
+            .with_user_agent(!self.no_user_agent)
+            .with_engine(self.engine)
+            .with_architecture(self.architecture)
+            .with_bot(self.bot)
+            .with_unicode_support(!self.no_unicode)
+            .with_literal_prefilter(self.literal_prefilter)
+            .with_lenient_loading(self.lenient)
+    }
+}
+
+fn main() -> ExitCode {
+    match run(&Cli::parse()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("uaparser: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(cli: &Cli) -> Result<(), Box<dyn Error>> {
+    let parser = build_parser(cli.parser.regexes.as_deref(), cli.parser.builder())?;
+    for error in parser.skipped_rules() {
+        eprintln!("uaparser: skipped {error}");
+    }
+    match &cli.command {
+        None => parse(&parser, &cli.parse),
+        Some(Command::Logs(args)) => logs(parser, args),
+    }
+}
+
+fn parse(parser: &UserAgentParser, args: &ParseArgs) -> Result<(), Box<dyn Error>> {
+    let stdout = io::stdout();
+    let mut output = Output::new(args.format, BufWriter::new(stdout.lock()));
+
+    if !args.user_agents.is_empty() {
+        for user_agent in &args.user_agents {
+            output.write(user_agent, &parser.parse(user_agent))?;
+        }
+    } else if args.files.is_empty() {
+        parse_lines(parser, io::stdin().lock(), &mut output)?;
+    } else {
+        for path in &args.files {
+            parse_lines(parser, open(path)?, &mut output)?;
+        }
+    }
+
+    output.finish()
+}
+
+fn logs(parser: UserAgentParser, args: &LogsArgs) -> Result<(), Box<dyn Error>> {
+    let parser = CachingParser::new(parser, args.cache_size);
+    let format = match args.log_format {
+        LogFormatArg::Combined => LogFormat::Combined,
+        LogFormatArg::W3c => LogFormat::W3c,
+        LogFormatArg::Jsonl => LogFormat::JsonLines(args.field.clone()),
+    };
+
+    let mut summary = LogSummary::default();
+    if args.files.is_empty() {
+        summary.ingest(&parser, io::stdin().lock(), &format)?;
+    }
+    for path in &args.files {
+        summary
+            .ingest(&parser, open(path)?, &format)
+            .map_err(|e| format!("cannot read {}: {e}", path.display()))?;
+    }
+
+    let stdout = io::stdout();
+    let mut writer = BufWriter::new(stdout.lock());
+    write_summary(&mut writer, args.format, &summary, args.top)?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Opens `path` for reading, where `-` stands for stdin.
+fn open(path: &Path) -> Result<Box<dyn BufRead>, Box<dyn Error>> {
+    if path.as_os_str() == "-" {
+        return Ok(Box::new(io::stdin().lock()));
+    }
+    let file =
+        File::open(path).map_err(|e| format!("cannot open {}: {e}", path.display()))?;
+    Ok(Box::new(BufReader::new(file)))
+}
+
+/// Parses every non-empty line of `reader` as a user agent string. Bytes
+/// that are not valid UTF-8 are replaced rather than ending the run.
+fn parse_lines<W: Write>(
+    parser: &UserAgentParser,
+    mut reader: impl BufRead,
+    output: &mut Output<W>,
+) -> io::Result<()> {
+    let mut buffer = Vec::new();
+    loop {
+        buffer.clear();
+        if reader.read_until(b'\n', &mut buffer)? == 0 {
+            return Ok(());
+        }
+
+        let line = String::from_utf8_lossy(&buffer);
+        let user_agent = line.trim();
+        if !user_agent.is_empty() {
+            output.write(user_agent, &parser.parse(user_agent))?;
+        }
+    }
+}
+
+fn build_parser(
+    regexes: Option<&str>,
+    builder: UserAgentParserBuilder,
+) -> Result<UserAgentParser, Box<dyn Error>> {
+    match regexes {
+        Some(path) => builder
+            .build_from_yaml(path)
+            .map_err(|e| format!("cannot load {path}: {e}").into()),
+        None => build_embedded(builder),
+    }
+}
+
+#[cfg(feature = "embedded-regexes")]
+fn build_embedded(
+    builder: UserAgentParserBuilder,
+) -> Result<UserAgentParser, Box<dyn Error>> {
+    Ok(builder.build_embedded()?)
+}
+
+#[cfg(not(feature = "embedded-regexes"))]
+fn build_embedded(_: UserAgentParserBuilder) -> Result<UserAgentParser, Box<dyn Error>> {
+    Err("no regexes file given; pass --regexes or build with the \
+         `embedded-regexes` feature"
+        .into())
+}
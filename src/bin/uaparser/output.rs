@@ -0,0 +1,282 @@
+use std::{
+    error::Error,
+    io::{self, Write},
+};
+
+use serde::Serialize;
+use uaparser::{access_log::LogSummary, Client, DeviceType, Dimension, Report};
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+pub enum Format {
+    /// One JSON object per line
+    Json,
+    /// Comma separated values with a header row
+    Csv,
+    /// Aligned columns, for reading in a terminal
+    Table,
+}
+
+/// The columns of the `Csv` and `Table` formats.
+const COLUMNS: [&str; 10] = [
+    "string",
+    "user_agent",
+    "os",
+    "device",
+    "brand",
+    "model",
+    "device_type",
+    "engine",
+    "architecture",
+    "bot",
+];
+
+/// A line of `Json` output.
+#[derive(Serialize)]
+struct Record<'a> {
+    string: &'a str,
+    #[serde(flatten)]
+    client: &'a Client<'a>,
+    device_type: DeviceType,
+}
+
+/// Writes parsed clients to `W` in one of the supported formats. Rows of a
+/// `Table` are held back until `finish`, as every row is needed to size the
+/// columns.
+pub struct Output<W> {
+    format: Format,
+    writer: W,
+    rows: Vec<[String; 10]>,
+    header_written: bool,
+}
+
+impl<W: Write> Output<W> {
+    pub fn new(format: Format, writer: W) -> Self {
+        Output {
+            format,
+            writer,
+            rows: Vec::new(),
+            header_written: false,
+        }
+    }
+
+    pub fn write(&mut self, string: &str, client: &Client<'_>) -> io::Result<()> {
+        match self.format {
+            Format::Json => {
+                let record = Record {
+                    string,
+                    client,
+                    device_type: client.device_type(),
+                };
+                serde_json::to_writer(&mut self.writer, &record)?;
+                writeln!(self.writer)
+            }
+            Format::Csv => {
+                if !self.header_written {
+                    self.header_written = true;
+                    write_csv_row(&mut self.writer, &COLUMNS)?;
+                }
+                write_csv_row(&mut self.writer, &row(string, client))
+            }
+            Format::Table => {
+                self.rows.push(row(string, client));
+                Ok(())
+            }
+        }
+    }
+
+    pub fn finish(mut self) -> Result<(), Box<dyn Error>> {
+        if let Format::Table = self.format {
+            write_table(&mut self.writer, &COLUMNS, &self.rows)?;
+        }
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+/// Writes the `n` most frequent browsers, operating systems and devices of
+/// `summary`. `Json` output has every dimension of `UsageStats` instead.
+pub fn write_summary(
+    writer: &mut impl Write,
+    format: Format,
+    summary: &LogSummary,
+    n: usize,
+) -> io::Result<()> {
+    let dimensions = [
+        ("browser", Dimension::BrowserVersion),
+        ("os", Dimension::OsVersion),
+        ("device", Dimension::Device),
+        ("device_type", Dimension::DeviceType),
+    ];
+
+    match format {
+        Format::Json => {
+            #[derive(Serialize)]
+            struct Json {
+                lines: u64,
+                missing: u64,
+                malformed: u64,
+                #[serde(flatten)]
+                report: Report,
+            }
+
+            let json = Json {
+                lines: summary.lines,
+                missing: summary.missing,
+                malformed: summary.malformed,
+                report: summary.stats.report(n),
+            };
+            serde_json::to_writer_pretty(&mut *writer, &json)?;
+            writeln!(writer)
+        }
+        Format::Csv => {
+            write_csv_row(writer, &["dimension", "value", "count", "percent"])?;
+            for (name, dimension) in dimensions {
+                for share in summary.stats.top(dimension, n) {
+                    write_csv_row(
+                        writer,
+                        &[
+                            name,
+                            &share.value,
+                            &share.count.to_string(),
+                            &format!("{:.1}", share.percent),
+                        ],
+                    )?;
+                }
+            }
+            Ok(())
+        }
+        Format::Table => {
+            writeln!(
+                writer,
+                "{} lines, {} without a user agent, {} malformed",
+                summary.lines, summary.missing, summary.malformed
+            )?;
+            for (name, dimension) in dimensions {
+                let rows: Vec<_> = summary
+                    .stats
+                    .top(dimension, n)
+                    .into_iter()
+                    .map(|share| {
+                        [
+                            share.value,
+                            share.count.to_string(),
+                            format!("{:.1}%", share.percent),
+                        ]
+                    })
+                    .collect();
+                writeln!(writer)?;
+                write_table(writer, &[name, "count", "share"], &rows)?;
+            }
+            Ok(())
+        }
+    }
+}
+
+fn row(string: &str, client: &Client<'_>) -> [String; 10] {
+    let with_version = |family: &str, version: Option<String>| match version {
+        Some(version) => format!("{family} {version}"),
+        None => family.to_owned(),
+    };
+
+    [
+        string.to_owned(),
+        with_version(
+            &client.user_agent.family,
+            client.user_agent.version().map(|v| v.to_string()),
+        ),
+        with_version(
+            &client.os.family,
+            client.os.version().map(|v| v.to_string()),
+        ),
+        client.device.family.to_string(),
+        client
+            .device
+            .brand
+            .as_deref()
+            .unwrap_or_default()
+            .to_owned(),
+        client
+            .device
+            .model
+            .as_deref()
+            .unwrap_or_default()
+            .to_owned(),
+        client.device_type().as_str().to_owned(),
+        with_version(
+            &client.engine.family,
+            client.engine.version().map(|v| v.to_string()),
+        ),
+        client.architecture.family.to_string(),
+        client
+            .bot
+            .as_ref()
+            .map(|bot| bot.name.to_string())
+            .unwrap_or_default(),
+    ]
+}
+
+fn write_csv_row<S: AsRef<str>>(writer: &mut impl Write, fields: &[S]) -> io::Result<()> {
+    for (i, field) in fields.iter().enumerate() {
+        if i > 0 {
+            write!(writer, ",")?;
+        }
+        let field = field.as_ref();
+        if field.contains([',', '"', '\n', '\r']) {
+            write!(writer, "\"{}\"", field.replace('"', "\"\""))?;
+        } else {
+            write!(writer, "{field}")?;
+        }
+    }
+    writeln!(writer)
+}
+
+fn write_table<const N: usize>(
+    writer: &mut impl Write,
+    columns: &[&str; N],
+    rows: &[[String; N]],
+) -> io::Result<()> {
+    let mut widths = columns.map(|column| column.chars().count());
+    for row in rows {
+        for (width, field) in widths.iter_mut().zip(row) {
+            *width = (*width).max(field.chars().count());
+        }
+    }
+
+    let header = columns.map(str::to_owned);
+    for row in std::iter::once(&header).chain(rows) {
+        let line = row
+            .iter()
+            .zip(widths)
+            .map(|(field, width)| format!("{field:width$}"))
+            .collect::<Vec<_>>()
+            .join("  ");
+        writeln!(writer, "{}", line.trim_end())?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quotes_csv_fields() {
+        let mut csv = Vec::new();
+        write_csv_row(&mut csv, &["plain", "a, b", "say \"hi\""]).unwrap();
+        assert_eq!(csv, b"plain,\"a, b\",\"say \"\"hi\"\"\"\n");
+    }
+
+    #[test]
+    fn aligns_table_columns() {
+        let mut row = COLUMNS.map(|_| String::new());
+        row[0] = String::from("a-rather-long-string");
+        row[1] = String::from("Firefox");
+
+        let mut table = Vec::new();
+        write_table(&mut table, &COLUMNS, &[row]).unwrap();
+        let table = String::from_utf8(table).unwrap();
+        let lines: Vec<_> = table.lines().collect();
+        assert!(lines[0].starts_with("string                user_agent"));
+        assert_eq!(lines[1], "a-rather-long-string  Firefox");
+    }
+}
@@ -0,0 +1,43 @@
+use super::{Deserialize, Serialize};
+use std::{borrow::Cow, sync::Arc};
+
+/// Describes an automated agent, such as a crawler or an HTTP library, by its
+/// `Name`, `Category` and the `Vendor` that operates it.
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, Hash, PartialEq)]
+pub struct BotInfo<'a> {
+    pub name: Cow<'a, str>,
+    pub category: BotCategory,
+    /// Shared with the rule that matched, so classifying a bot never copies it
+    pub vendor: Option<Arc<str>>,
+}
+
+impl BotInfo<'_> {
+    /// Converts this into a `BotInfo` that owns all of its data, so it can
+    /// outlive the user agent string it was parsed from.
+    #[must_use]
+    pub fn into_owned(self) -> BotInfo<'static> {
+        BotInfo {
+            name: Cow::Owned(self.name.into_owned()),
+            category: self.category,
+            vendor: self.vendor,
+        }
+    }
+}
+
+/// The kind of automated agent a `BotInfo` describes.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, Eq, Hash, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum BotCategory {
+    /// Crawlers that index pages for a search engine, like Googlebot
+    SearchEngine,
+    /// Uptime and performance checks, like Pingdom
+    Monitoring,
+    /// Crawlers that collect backlink and ranking data, like `AhrefsBot`
+    SeoTool,
+    /// Browsers driven by automation, like Headless Chrome
+    HeadlessBrowser,
+    /// Generic HTTP clients, like curl or python-requests
+    HttpLibrary,
+    /// Any other crawler, including those only recognized as a `Spider` device
+    Crawler,
+}
@@ -0,0 +1,366 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex, PoisonError,
+    },
+};
+
+use super::{
+    client_hints, Client, ClientHints, Device, Parser, UserAgent, UserAgentParser, OS,
+};
+
+/// Wraps a `Parser` and remembers the `Client` it returned for the most
+/// recently parsed user agent strings, so parsing a string again only costs
+/// a hash lookup. `parse_shared` hands out the cached result itself, while
+/// `Parser::parse` returns a copy of it.
+///
+/// The cache holds up to `capacity` owned results and evicts the least
+/// recently used one when full. It is safe to share between threads; parsing
+/// itself happens outside the lock, so a slow parse never blocks lookups.
+///
+/// Only the User-Agent strings passed in are cached. The device lookups that
+/// `parse_with_client_hints` runs for a `Sec-CH-UA-Model` hint go straight to
+/// the wrapped parser, so they never push out real traffic.
+///
+/// ```rust
+/// # use uaparser::*;
+/// let regexes = br"
+/// user_agent_parsers:
+///   - regex: '(Firefox)/(\d+)\.(\d+)'
+/// os_parsers: []
+/// device_parsers: []
+/// ";
+/// let parser = CachingParser::new(UserAgentParser::from_bytes(regexes).unwrap(), 1024);
+///
+/// let client = parser.parse("Mozilla/5.0 Firefox/115.0");
+/// assert_eq!(client, parser.parse("Mozilla/5.0 Firefox/115.0"));
+/// assert_eq!((parser.hits(), parser.misses()), (1, 1));
+/// ```
+#[derive(Debug)]
+pub struct CachingParser<P = UserAgentParser> {
+    parser: P,
+    cache: Mutex<Lru>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl<P: Parser> CachingParser<P> {
+    /// Creates a cache of up to `capacity` results in front of `parser`. A
+    /// capacity of zero disables caching.
+    pub fn new(parser: P, capacity: usize) -> Self {
+        CachingParser {
+            parser,
+            cache: Mutex::new(Lru::new(capacity)),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    /// Returns the wrapped parser.
+    pub fn inner(&self) -> &P {
+        &self.parser
+    }
+
+    /// Consumes the cache, returning the wrapped parser.
+    pub fn into_inner(self) -> P {
+        self.parser
+    }
+
+    /// The maximum number of results kept in the cache.
+    pub fn capacity(&self) -> usize {
+        self.lock().capacity
+    }
+
+    /// The number of results currently in the cache.
+    pub fn len(&self) -> usize {
+        self.lock().map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The number of lookups answered from the cache.
+    pub fn hits(&self) -> u64 {
+        self.hits.load(Ordering::Relaxed)
+    }
+
+    /// The number of lookups that had to run the wrapped parser.
+    pub fn misses(&self) -> u64 {
+        self.misses.load(Ordering::Relaxed)
+    }
+
+    /// Drops every cached result and resets the hit and miss counters.
+    pub fn clear(&self) {
+        self.lock().clear();
+        self.hits.store(0, Ordering::Relaxed);
+        self.misses.store(0, Ordering::Relaxed);
+    }
+
+    /// Returns the cached `Client` for a user agent string, parsing and
+    /// caching it first if it has not been seen recently. Unlike
+    /// `Parser::parse` this never copies the result.
+    pub fn parse_shared(&self, user_agent: &str) -> Arc<Client<'static>> {
+        if let Some(client) = self.lock().get(user_agent) {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return client;
+        }
+
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        let client = Arc::new(self.parser.parse(user_agent).into_owned());
+        self.lock().insert(user_agent, Arc::clone(&client));
+        client
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Lru> {
+        // A panic while holding the lock cannot leave the list half-updated
+        // in a way that matters more than losing a few entries.
+        self.cache.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl<P: Parser> Parser for CachingParser<P> {
+    /// Returns the cached `Client` for a user agent string, parsing and
+    /// caching it first if it has not been seen recently.
+    fn parse<'a>(&self, user_agent: &'a str) -> Client<'a> {
+        Client::clone(&self.parse_shared(user_agent))
+    }
+
+    fn parse_device<'a>(&self, user_agent: &'a str) -> Device<'a> {
+        self.parse(user_agent).device
+    }
+
+    fn parse_os<'a>(&self, user_agent: &'a str) -> OS<'a> {
+        self.parse(user_agent).os
+    }
+
+    fn parse_user_agent<'a>(&self, user_agent: &'a str) -> UserAgent<'a> {
+        self.parse(user_agent).user_agent
+    }
+
+    fn parse_with_client_hints<'a>(
+        &self,
+        user_agent: &'a str,
+        hints: &ClientHints<'_>,
+    ) -> Client<'a> {
+        client_hints::merge(&self.parser, self.parse(user_agent), hints)
+    }
+}
+
+/// Marks the end of the recency list.
+const NIL: usize = usize::MAX;
+
+/// A least recently used cache of owned `Client`s, kept as a doubly linked
+/// list threaded through a `Vec` so evicted slots are reused in place.
+#[derive(Debug)]
+struct Lru {
+    capacity: usize,
+    map: HashMap<Arc<str>, usize>,
+    nodes: Vec<Node>,
+    /// The most recently used node
+    head: usize,
+    /// The least recently used node, evicted first
+    tail: usize,
+}
+
+#[derive(Debug)]
+struct Node {
+    key: Arc<str>,
+    client: Arc<Client<'static>>,
+    prev: usize,
+    next: usize,
+}
+
+impl Lru {
+    fn new(capacity: usize) -> Self {
+        Lru {
+            capacity,
+            map: HashMap::new(),
+            nodes: Vec::new(),
+            head: NIL,
+            tail: NIL,
+        }
+    }
+
+    fn get(&mut self, key: &str) -> Option<Arc<Client<'static>>> {
+        let index = *self.map.get(key)?;
+        self.unlink(index);
+        self.push_front(index);
+        Some(Arc::clone(&self.nodes[index].client))
+    }
+
+    fn insert(&mut self, key: &str, client: Arc<Client<'static>>) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        if let Some(&index) = self.map.get(key) {
+            self.nodes[index].client = client;
+            self.unlink(index);
+            self.push_front(index);
+            return;
+        }
+
+        let key: Arc<str> = Arc::from(key);
+        let index = if self.nodes.len() < self.capacity {
+            self.nodes.push(Node {
+                key: Arc::clone(&key),
+                client,
+                prev: NIL,
+                next: NIL,
+            });
+            self.nodes.len() - 1
+        } else {
+            let index = self.tail;
+            self.unlink(index);
+            let node = &mut self.nodes[index];
+            self.map.remove(&node.key);
+            node.key = Arc::clone(&key);
+            node.client = client;
+            index
+        };
+
+        self.map.insert(key, index);
+        self.push_front(index);
+    }
+
+    fn clear(&mut self) {
+        self.map.clear();
+        self.nodes.clear();
+        self.head = NIL;
+        self.tail = NIL;
+    }
+
+    fn unlink(&mut self, index: usize) {
+        let Node { prev, next, .. } = self.nodes[index];
+        if prev == NIL {
+            self.head = next;
+        } else {
+            self.nodes[prev].next = next;
+        }
+        if next == NIL {
+            self.tail = prev;
+        } else {
+            self.nodes[next].prev = prev;
+        }
+    }
+
+    fn push_front(&mut self, index: usize) {
+        self.nodes[index].prev = NIL;
+        self.nodes[index].next = self.head;
+        if self.head == NIL {
+            self.tail = index;
+        } else {
+            self.nodes[self.head].prev = index;
+        }
+        self.head = index;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const REGEXES: &str = r"
+user_agent_parsers:
+  - regex: '(Firefox)/(\d+)\.(\d+)'
+  - regex: '(Chrome)/(\d+)\.(\d+)'
+os_parsers:
+  - regex: '(Linux)'
+device_parsers: []
+";
+
+    fn parser(capacity: usize) -> CachingParser {
+        let parser = UserAgentParser::from_bytes(REGEXES.as_bytes())
+            .expect("Parser creation failed");
+        CachingParser::new(parser, capacity)
+    }
+
+    #[test]
+    fn counts_hits_and_misses() {
+        let parser = parser(8);
+        let ua = String::from("Mozilla/5.0 (X11; Linux x86_64) Firefox/115.0");
+
+        let first = parser.parse(&ua);
+        let second = parser.parse(&ua);
+        assert_eq!(first, second);
+        assert_eq!(first, parser.inner().parse(&ua));
+        assert_eq!(parser.parse_os(&ua).family, "Linux");
+        assert_eq!((parser.hits(), parser.misses()), (2, 1));
+        assert_eq!(parser.len(), 1);
+
+        parser.clear();
+        assert!(parser.is_empty());
+        assert_eq!((parser.hits(), parser.misses()), (0, 0));
+    }
+
+    #[test]
+    fn shares_cached_results() {
+        let parser = parser(8);
+        let first = parser.parse_shared("Firefox/115.0");
+        let second = parser.parse_shared("Firefox/115.0");
+        assert!(Arc::ptr_eq(&first, &second));
+        assert_eq!(*first, parser.parse("Firefox/115.0"));
+        assert_eq!((parser.hits(), parser.misses()), (2, 1));
+    }
+
+    #[test]
+    fn client_hint_lookups_are_not_cached() {
+        let parser = parser(8);
+        let ua = "Mozilla/5.0 (Linux; Android 10; K) Chrome/118.0.0.0 Mobile";
+        let hints = ClientHints::from_headers([("Sec-CH-UA-Model", r#""Pixel 7""#)]);
+
+        let client = parser.parse_with_client_hints(ua, &hints);
+        assert_eq!(client, parser.inner().parse_with_client_hints(ua, &hints));
+        assert_eq!(client.device.model.as_deref(), Some("Pixel 7"));
+        assert_eq!(parser.len(), 1);
+        assert_eq!((parser.hits(), parser.misses()), (0, 1));
+    }
+
+    #[test]
+    fn evicts_least_recently_used() {
+        let parser = parser(2);
+        parser.parse("Firefox/1.0");
+        parser.parse("Firefox/2.0");
+        parser.parse("Firefox/1.0");
+        parser.parse("Chrome/3.0");
+        assert_eq!(parser.len(), 2);
+        assert_eq!(parser.misses(), 3);
+
+        // `Firefox/2.0` was the least recently used when `Chrome/3.0` arrived.
+        parser.parse("Firefox/1.0");
+        parser.parse("Chrome/3.0");
+        assert_eq!(parser.hits(), 3);
+        parser.parse("Firefox/2.0");
+        assert_eq!(parser.misses(), 4);
+    }
+
+    #[test]
+    fn zero_capacity_disables_caching() {
+        let parser = parser(0);
+        parser.parse("Firefox/1.0");
+        parser.parse("Firefox/1.0");
+        assert_eq!((parser.hits(), parser.misses()), (0, 2));
+        assert!(parser.is_empty());
+    }
+
+    #[test]
+    fn shared_between_threads() {
+        fn family<P: Parser>(parser: &P, ua: &str) -> String {
+            parser.parse_user_agent(ua).family.into_owned()
+        }
+
+        let parser = Arc::new(parser(16));
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let parser = Arc::clone(&parser);
+                std::thread::spawn(move || family(&*parser, "Chrome/114.0"))
+            })
+            .collect();
+        for handle in handles {
+            assert_eq!(handle.join().unwrap(), "Chrome");
+        }
+        assert_eq!(parser.hits() + parser.misses(), 4);
+    }
+}
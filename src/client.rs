@@ -1,13 +1,95 @@
+use super::Architecture;
+use super::BotInfo;
 use super::Device;
+use super::DeviceType;
+use super::Engine;
 use super::UserAgent;
 use super::OS;
-use super::{Deserialize, Serialize};
+use super::{device_type, Deserialize, Serialize};
 
-/// Houses the `Device`, `OS`, and `UserAgent` structs, which each get parsed
-/// out from a user agent string by a `UserAgentParser`.
+/// Houses the `Device`, `OS`, `UserAgent`, `Engine` and `Architecture`
+/// structs, which each get parsed out from a user agent string by a
+/// `UserAgentParser`.
+///
+/// A `Client` borrows from the string it was parsed from where it can. Use
+/// `into_owned` to keep it around longer or send it to another thread; the
+/// owned `Client<'static>` also deserializes without borrowing from its input.
+///
+/// More fields may be added in minor releases, so outside of this crate a
+/// `Client` is built with `Client::new` rather than a struct literal.
 #[derive(Clone, Debug, Serialize, Deserialize, Eq, Hash, PartialEq)]
+#[non_exhaustive]
 pub struct Client<'a> {
     pub device: Device<'a>,
     pub os: OS<'a>,
     pub user_agent: UserAgent<'a>,
+    /// Only filled in by parsers built with `with_engine(true)`
+    #[serde(default)]
+    pub engine: Engine<'a>,
+    /// Only filled in by parsers built with `with_architecture(true)`
+    #[serde(default)]
+    pub architecture: Architecture<'a>,
+    /// Set when the user agent belongs to a crawler, monitoring service, HTTP
+    /// library or other automated agent. Only parsers built with
+    /// `with_bot(true)` fill this in.
+    #[serde(default)]
+    pub bot: Option<BotInfo<'a>>,
+}
+
+impl<'a> Client<'a> {
+    /// Creates a `Client` from the results of the three core sub-parsers,
+    /// with no engine, architecture or bot info. A custom `Parser` returns
+    /// one of these, setting any other fields afterwards.
+    ///
+    /// ```rust
+    /// # use uaparser::*;
+    /// let user_agent = UserAgent {
+    ///     family: "curl".into(),
+    ///     ..UserAgent::default()
+    /// };
+    /// let client = Client::new(Device::default(), OS::default(), user_agent);
+    /// assert_eq!(client.user_agent.family, "curl");
+    /// assert!(!client.is_bot());
+    /// ```
+    #[must_use]
+    pub fn new(device: Device<'a>, os: OS<'a>, user_agent: UserAgent<'a>) -> Self {
+        Client {
+            device,
+            os,
+            user_agent,
+            engine: Engine::default(),
+            architecture: Architecture::default(),
+            bot: None,
+        }
+    }
+}
+
+impl Client<'_> {
+    /// Returns whether the user agent was classified as a bot.
+    #[must_use]
+    pub fn is_bot(&self) -> bool {
+        self.bot.is_some()
+    }
+
+    /// Returns the form factor of the device, such as a tablet or a smart TV,
+    /// derived from the parsed `Device`, `OS` and `UserAgent`. Bots, and
+    /// clients whose fields give no hint, are `DeviceType::Unknown`.
+    #[must_use]
+    pub fn device_type(&self) -> DeviceType {
+        device_type::classify(self)
+    }
+
+    /// Converts this into a `Client` that owns all of its data, so it can
+    /// outlive the user agent string it was parsed from.
+    #[must_use]
+    pub fn into_owned(self) -> Client<'static> {
+        Client {
+            device: self.device.into_owned(),
+            os: self.os.into_owned(),
+            user_agent: self.user_agent.into_owned(),
+            engine: self.engine.into_owned(),
+            architecture: self.architecture.into_owned(),
+            bot: self.bot.map(BotInfo::into_owned),
+        }
+    }
 }
@@ -0,0 +1,666 @@
+//! Support for User-Agent Client Hints.
+//!
+//! Chromium based browsers freeze most of the User-Agent string, reporting
+//! `Windows NT 10.0` for Windows 11 and `K` in place of the Android model. The
+//! real values are available through the `Sec-CH-UA-*` request headers, which
+//! `Parser::parse_with_client_hints` merges with the result of parsing the
+//! User-Agent string.
+//!
+//! Any hint that is present and well-formed takes precedence over the value
+//! parsed from the User-Agent string, and anything missing or malformed falls
+//! back to it:
+//!
+//! - `Sec-CH-UA-Full-Version-List`, or failing that `Sec-CH-UA`, replaces the
+//!   `UserAgent` family and version. GREASE brands are ignored, and `Chromium`
+//!   is only used when no other brand is listed. If the parsed family already
+//!   names the brand, such as `Chrome Mobile` for `Google Chrome`, the family
+//!   is kept. A major-only version from `Sec-CH-UA` only replaces the parsed
+//!   version when the major versions disagree.
+//! - `Sec-CH-UA-Platform` replaces the `OS` family, and
+//!   `Sec-CH-UA-Platform-Version` its version. Windows platform versions are
+//!   mapped to the marketing versions, so `15.0.0` becomes `11`.
+//! - `Sec-CH-UA-Model` replaces the `Device` with the one the device regexes
+//!   report for that model, or with the bare model if none of them match.
+//! - `Sec-CH-UA-Mobile` only marks an otherwise unknown device as a
+//!   `Generic Smartphone`.
+
+use std::borrow::Cow;
+
+use super::{Client, Device, Parser, OS};
+
+/// The raw values of the User-Agent Client Hints request headers.
+///
+/// ```rust
+/// # use uaparser::*;
+/// let hints = ClientHints::from_headers([
+///     ("Sec-CH-UA", r#""Chromium";v="118", "Google Chrome";v="118", "Not=A?Brand";v="99""#),
+///     ("Sec-CH-UA-Platform", r#""Windows""#),
+///     ("Sec-CH-UA-Platform-Version", r#""15.0.0""#),
+///     ("Accept", "text/html"),
+/// ]);
+/// assert_eq!(hints.platform.as_deref(), Some(r#""Windows""#));
+/// ```
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ClientHints<'a> {
+    /// `Sec-CH-UA`, the brands and their major versions.
+    pub brands: Option<Cow<'a, str>>,
+    /// `Sec-CH-UA-Full-Version-List`, the brands and their full versions.
+    pub full_version_list: Option<Cow<'a, str>>,
+    /// `Sec-CH-UA-Platform`
+    pub platform: Option<Cow<'a, str>>,
+    /// `Sec-CH-UA-Platform-Version`
+    pub platform_version: Option<Cow<'a, str>>,
+    /// `Sec-CH-UA-Model`
+    pub model: Option<Cow<'a, str>>,
+    /// `Sec-CH-UA-Mobile`
+    pub mobile: Option<Cow<'a, str>>,
+}
+
+impl<'a> ClientHints<'a> {
+    /// Collects the client hint headers from a list of request headers,
+    /// ignoring any others. Header names are matched case-insensitively.
+    pub fn from_headers<I, K, V>(headers: I) -> Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: AsRef<str>,
+        V: Into<Cow<'a, str>>,
+    {
+        let mut hints = ClientHints::default();
+        for (name, value) in headers {
+            let slot = match name.as_ref().to_ascii_lowercase().as_str() {
+                "sec-ch-ua" => &mut hints.brands,
+                "sec-ch-ua-full-version-list" => &mut hints.full_version_list,
+                "sec-ch-ua-platform" => &mut hints.platform,
+                "sec-ch-ua-platform-version" => &mut hints.platform_version,
+                "sec-ch-ua-model" => &mut hints.model,
+                "sec-ch-ua-mobile" => &mut hints.mobile,
+                _ => continue,
+            };
+            *slot = Some(value.into());
+        }
+        hints
+    }
+}
+
+/// Merges `hints` into `client`, which was parsed from the User-Agent string.
+pub(crate) fn merge<'a, P: Parser + ?Sized>(
+    parser: &P,
+    mut client: Client<'a>,
+    hints: &ClientHints<'_>,
+) -> Client<'a> {
+    if let Some((brand, version, full)) = significant_brand(hints) {
+        let family = brand_family(&brand, is_mobile(hints) == Some(true));
+        if !client.user_agent.family.starts_with(family) {
+            client.user_agent.family = Cow::Owned(family.to_owned());
+            client.user_agent.major = None;
+            client.user_agent.minor = None;
+            client.user_agent.patch = None;
+        }
+
+        let mut parts = version.split('.').map(|x| Cow::Owned(x.to_owned()));
+        let major = parts.next();
+        if full {
+            client.user_agent.major = major;
+            client.user_agent.minor = parts.next();
+            client.user_agent.patch = parts.next();
+        } else if client.user_agent.major != major {
+            client.user_agent.major = major;
+            client.user_agent.minor = None;
+            client.user_agent.patch = None;
+        }
+    }
+
+    if let Some(platform) = string_hint(hints.platform.as_deref()) {
+        if let Some(family) = platform_family(&platform) {
+            let version = string_hint(hints.platform_version.as_deref());
+            if client.os.family != family {
+                client.os = OS {
+                    family: Cow::Borrowed(family),
+                    ..OS::default()
+                };
+            }
+            // A version that maps to no release, such as the `0.0.0` Windows 7
+            // and 8 send, leaves the one parsed from the User-Agent alone.
+            if let Some(os) = version.and_then(|version| platform_os(family, &version)) {
+                client.os = os;
+            }
+        }
+    }
+
+    if let Some(model) = string_hint(hints.model.as_deref()) {
+        if !model.is_empty() {
+            client.device = model_device(parser, &model);
+        }
+    } else if client.device.family == "Other" && is_mobile(hints) == Some(true) {
+        client.device.family = Cow::Borrowed("Generic Smartphone");
+    }
+
+    client
+}
+
+/// Picks the brand that best describes the browser, along with its version
+/// and whether that version came from the full version list.
+fn significant_brand(hints: &ClientHints<'_>) -> Option<(String, String, bool)> {
+    let (brands, full) = match hints.full_version_list.as_deref().and_then(parse_brands) {
+        Some(brands) => (brands, true),
+        None => (hints.brands.as_deref().and_then(parse_brands)?, false),
+    };
+
+    let mut brands: Vec<_> = brands
+        .into_iter()
+        .filter(|(brand, _)| !is_grease(brand))
+        .collect();
+    let position = brands
+        .iter()
+        .position(|(brand, _)| brand != "Chromium")
+        .unwrap_or(0);
+
+    if position < brands.len() {
+        let (brand, version) = brands.swap_remove(position);
+        Some((brand, version, full))
+    } else {
+        None
+    }
+}
+
+/// Parses a brand list like `"Google Chrome";v="118"` into brands and
+/// versions, skipping any brand without a version.
+fn parse_brands(header: &str) -> Option<Vec<(String, String)>> {
+    let list = structured::parse_list(header)?;
+    Some(
+        list.into_iter()
+            .filter_map(|(item, params)| {
+                let brand = item.into_string()?;
+                let version = params
+                    .into_iter()
+                    .find(|(key, _)| key == "v")
+                    .and_then(|(_, value)| value.into_string())?;
+                Some((brand, version))
+            })
+            .collect(),
+    )
+}
+
+/// Chromium sends a made-up brand such as `Not=A?Brand` to keep servers from
+/// relying on the exact list. It is always `Not`, `A` and `Brand` separated by
+/// punctuation or spaces, sometimes with a leading space.
+fn is_grease(brand: &str) -> bool {
+    fn separated(text: &str) -> Option<&str> {
+        let rest =
+            text.trim_start_matches(|c: char| c == ' ' || c.is_ascii_punctuation());
+        (rest.len() < text.len()).then_some(rest)
+    }
+
+    brand
+        .trim_start()
+        .strip_prefix("Not")
+        .and_then(separated)
+        .and_then(|rest| rest.strip_prefix('A'))
+        .and_then(separated)
+        == Some("Brand")
+}
+
+fn brand_family(brand: &str, mobile: bool) -> &str {
+    match (brand, mobile) {
+        ("Google Chrome", false) => "Chrome",
+        ("Google Chrome", true) => "Chrome Mobile",
+        ("Microsoft Edge", false) => "Edge",
+        ("Microsoft Edge", true) => "Edge Mobile",
+        ("Android WebView", _) => "Chrome Mobile WebView",
+        ("Yandex", _) => "Yandex Browser",
+        _ => brand,
+    }
+}
+
+fn platform_family(platform: &str) -> Option<&'static str> {
+    Some(match platform {
+        "Windows" => "Windows",
+        "macOS" => "Mac OS X",
+        "Android" => "Android",
+        "iOS" => "iOS",
+        "Chrome OS" | "Chromium OS" => "Chrome OS",
+        "Linux" => "Linux",
+        "Fuchsia" => "Fuchsia",
+        _ => return None,
+    })
+}
+
+fn platform_os(family: &'static str, version: &str) -> Option<OS<'static>> {
+    let (major, minor, patch) = if family == "Windows" {
+        // Windows reports the version of its UI platform rather than the
+        // release, so it has to be mapped back to the familiar numbers.
+        let mut parts = version.split('.').map(|x| x.parse::<u32>().ok());
+        match (parts.next().flatten(), parts.next().flatten()) {
+            (Some(0), Some(1)) => (Some("7"), None, None),
+            (Some(0), Some(2)) => (Some("8"), None, None),
+            (Some(0), Some(3)) => (Some("8"), Some("1"), None),
+            (Some(1..=12), _) => (Some("10"), None, None),
+            (Some(13..), _) => (Some("11"), None, None),
+            _ => (None, None, None),
+        }
+    } else {
+        let mut parts = version.split('.').filter(|x| !x.is_empty());
+        (parts.next(), parts.next(), parts.next())
+    };
+
+    Some(OS {
+        family: Cow::Borrowed(family),
+        major: Some(Cow::Owned(major?.to_owned())),
+        minor: minor.map(|x| Cow::Owned(x.to_owned())),
+        patch: patch.map(|x| Cow::Owned(x.to_owned())),
+        patch_minor: None,
+    })
+}
+
+/// Runs the model through the device regexes by dressing it up the way
+/// Android browsers used to report it, so the result matches what parsing an
+/// unreduced User-Agent string from the same device gives.
+fn model_device<P: Parser + ?Sized>(parser: &P, model: &str) -> Device<'static> {
+    let user_agent = format!("Mozilla/5.0 (Linux; Android 10; {model} Build/)");
+    let device = parser.parse_device(&user_agent).into_owned();
+
+    if device.family == "Other" {
+        Device {
+            family: Cow::Owned(model.to_owned()),
+            brand: None,
+            model: Some(Cow::Owned(model.to_owned())),
+        }
+    } else {
+        device
+    }
+}
+
+fn string_hint(header: Option<&str>) -> Option<String> {
+    structured::parse_item(header?)?.0.into_string()
+}
+
+fn is_mobile(hints: &ClientHints<'_>) -> Option<bool> {
+    match structured::parse_item(hints.mobile.as_deref()?)?.0 {
+        structured::Item::Boolean(mobile) => Some(mobile),
+        _ => None,
+    }
+}
+
+/// Just enough of RFC 8941 Structured Field Values to read the client hints.
+mod structured {
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    pub(super) enum Item {
+        String(String),
+        Token(String),
+        Integer(i64),
+        Boolean(bool),
+    }
+
+    impl Item {
+        pub(super) fn into_string(self) -> Option<String> {
+            match self {
+                Item::String(value) => Some(value),
+                _ => None,
+            }
+        }
+    }
+
+    pub(super) type Params = Vec<(String, Item)>;
+
+    /// Parses an `sf-item`, such as `"Windows"` or `?1`.
+    pub(super) fn parse_item(input: &str) -> Option<(Item, Params)> {
+        let mut cursor = Cursor::new(input);
+        cursor.skip_spaces();
+        let item = cursor.item()?;
+        cursor.skip_spaces();
+        cursor.is_empty().then_some(item)
+    }
+
+    /// Parses an `sf-list` of items, such as the `Sec-CH-UA` brand list.
+    /// Inner lists are not supported.
+    pub(super) fn parse_list(input: &str) -> Option<Vec<(Item, Params)>> {
+        let mut cursor = Cursor::new(input);
+        let mut list = Vec::new();
+
+        cursor.skip_spaces();
+        while !cursor.is_empty() {
+            list.push(cursor.item()?);
+            cursor.skip_whitespace();
+            if cursor.is_empty() {
+                break;
+            }
+            cursor.expect(b',')?;
+            cursor.skip_whitespace();
+            if cursor.is_empty() {
+                return None;
+            }
+        }
+
+        Some(list)
+    }
+
+    struct Cursor<'a> {
+        input: &'a [u8],
+    }
+
+    impl<'a> Cursor<'a> {
+        fn new(input: &'a str) -> Self {
+            Cursor {
+                input: input.as_bytes(),
+            }
+        }
+
+        fn is_empty(&self) -> bool {
+            self.input.is_empty()
+        }
+
+        fn peek(&self) -> Option<u8> {
+            self.input.first().copied()
+        }
+
+        fn next(&mut self) -> Option<u8> {
+            let (&first, rest) = self.input.split_first()?;
+            self.input = rest;
+            Some(first)
+        }
+
+        fn expect(&mut self, byte: u8) -> Option<()> {
+            (self.next()? == byte).then_some(())
+        }
+
+        fn take_while(&mut self, f: impl Fn(u8) -> bool) -> &'a str {
+            let end = self
+                .input
+                .iter()
+                .position(|&b| !f(b))
+                .unwrap_or(self.input.len());
+            let (taken, rest) = self.input.split_at(end);
+            self.input = rest;
+            // Only ever called with ASCII predicates.
+            std::str::from_utf8(taken).unwrap_or_default()
+        }
+
+        fn skip_spaces(&mut self) {
+            self.take_while(|b| b == b' ');
+        }
+
+        fn skip_whitespace(&mut self) {
+            self.take_while(|b| b == b' ' || b == b'\t');
+        }
+
+        fn item(&mut self) -> Option<(Item, Params)> {
+            let item = self.bare_item()?;
+            let mut params = Vec::new();
+            while self.peek() == Some(b';') {
+                self.next();
+                self.skip_spaces();
+                let key = self.key()?;
+                let value = if self.peek() == Some(b'=') {
+                    self.next();
+                    self.bare_item()?
+                } else {
+                    Item::Boolean(true)
+                };
+                params.push((key, value));
+            }
+            Some((item, params))
+        }
+
+        fn bare_item(&mut self) -> Option<Item> {
+            match self.peek()? {
+                b'"' => self.string(),
+                b'?' => {
+                    self.next();
+                    match self.next()? {
+                        b'1' => Some(Item::Boolean(true)),
+                        b'0' => Some(Item::Boolean(false)),
+                        _ => None,
+                    }
+                }
+                b'-' | b'0'..=b'9' => {
+                    let negative = self.peek() == Some(b'-');
+                    if negative {
+                        self.next();
+                    }
+                    let digits = self.take_while(|b| b.is_ascii_digit());
+                    if digits.is_empty() || digits.len() > 15 || self.peek() == Some(b'.')
+                    {
+                        return None;
+                    }
+                    let value: i64 = digits.parse().ok()?;
+                    Some(Item::Integer(if negative { -value } else { value }))
+                }
+                b'A'..=b'Z' | b'a'..=b'z' | b'*' => {
+                    let token = self.take_while(|b| {
+                        b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~:/".contains(&b)
+                    });
+                    Some(Item::Token(token.to_owned()))
+                }
+                _ => None,
+            }
+        }
+
+        fn string(&mut self) -> Option<Item> {
+            self.expect(b'"')?;
+            let mut value = String::new();
+            loop {
+                match self.next()? {
+                    b'"' => return Some(Item::String(value)),
+                    b'\\' => match self.next()? {
+                        c @ (b'"' | b'\\') => value.push(char::from(c)),
+                        _ => return None,
+                    },
+                    c @ 0x20..=0x7e => value.push(char::from(c)),
+                    _ => return None,
+                }
+            }
+        }
+
+        fn key(&mut self) -> Option<String> {
+            if !matches!(self.peek()?, b'a'..=b'z' | b'*') {
+                return None;
+            }
+            let key = self.take_while(
+                |b| matches!(b, b'a'..=b'z' | b'0'..=b'9' | b'_' | b'-' | b'.' | b'*'),
+            );
+            Some(key.to_owned())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{structured::*, *};
+    use crate::UserAgentParser;
+
+    const REGEXES: &str = r"
+user_agent_parsers:
+  - regex: '(Edg)/(\d+)\.(\d+)\.(\d+)'
+    family_replacement: 'Edge'
+  - regex: 'Android.+(Chrome)/(\d+)\.(\d+)\.(\d+)'
+    family_replacement: 'Chrome Mobile'
+  - regex: '(Chrome)/(\d+)\.(\d+)\.(\d+)'
+os_parsers:
+  - regex: '(Windows NT 6\.1)'
+    os_replacement: 'Windows'
+    os_v1_replacement: '7'
+  - regex: '(Windows NT) (\d+)\.(\d+)'
+    os_replacement: 'Windows'
+    os_v1_replacement: '$2'
+  - regex: '(Android) (\d+)'
+device_parsers:
+  - regex: '; *(Pixel [^;/]+) Build/'
+    device_replacement: '$1'
+    brand_replacement: 'Google'
+    model_replacement: '$1'
+  - regex: '; *(SM-[^;/]+) Build/'
+    device_replacement: 'Samsung $1'
+    brand_replacement: 'Samsung'
+    model_replacement: '$1'
+  - regex: 'Android \d+; (K)\)'
+    device_replacement: 'Generic Smartphone'
+    brand_replacement: 'Generic_Android'
+";
+
+    fn parser() -> UserAgentParser {
+        UserAgentParser::from_bytes(REGEXES.as_bytes()).expect("Parser creation failed")
+    }
+
+    #[test]
+    fn parses_structured_headers() {
+        assert_eq!(
+            parse_list(r#""Chromium";v="118", "Not=A?Brand";v="99",token;a;b=?0"#),
+            Some(vec![
+                (
+                    Item::String("Chromium".into()),
+                    vec![("v".into(), Item::String("118".into()))]
+                ),
+                (
+                    Item::String("Not=A?Brand".into()),
+                    vec![("v".into(), Item::String("99".into()))]
+                ),
+                (
+                    Item::Token("token".into()),
+                    vec![
+                        ("a".into(), Item::Boolean(true)),
+                        ("b".into(), Item::Boolean(false))
+                    ]
+                ),
+            ])
+        );
+        assert_eq!(
+            parse_item(r#" "a \"quoted\" value" "#),
+            Some((Item::String(r#"a "quoted" value"#.into()), vec![]))
+        );
+        assert_eq!(parse_item("?1"), Some((Item::Boolean(true), vec![])));
+        assert_eq!(parse_item("-12"), Some((Item::Integer(-12), vec![])));
+        assert_eq!(parse_list(r#""a", "#), None);
+        assert_eq!(parse_item(r#""unterminated"#), None);
+    }
+
+    #[test]
+    fn hints_override_reduced_user_agent() {
+        let parser = parser();
+        let user_agent = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 \
+                          (KHTML, like Gecko) Chrome/118.0.0.0 Safari/537.36";
+        let hints = ClientHints::from_headers([
+            (
+                "sec-ch-ua-full-version-list",
+                r#""Chromium";v="118.0.5993.88", "Google Chrome";v="118.0.5993.88", "Not=A?Brand";v="99.0.0.0""#,
+            ),
+            ("Sec-CH-UA-Platform", r#""Windows""#),
+            ("Sec-CH-UA-Platform-Version", r#""15.0.0""#),
+            ("Sec-CH-UA-Mobile", "?0"),
+        ]);
+
+        let client = parser.parse_with_client_hints(user_agent, &hints);
+        assert_eq!(client.user_agent.family, "Chrome");
+        assert_eq!(client.user_agent.major.as_deref(), Some("118"));
+        assert_eq!(client.user_agent.patch.as_deref(), Some("5993"));
+        assert_eq!(client.os.family, "Windows");
+        assert_eq!(client.os.major.as_deref(), Some("11"));
+    }
+
+    #[test]
+    fn unmapped_platform_version_keeps_parsed_os() {
+        let parser = parser();
+        let user_agent = "Mozilla/5.0 (Windows NT 6.1; Win64; x64) Chrome/109.0.0.0";
+        for version in [r#""0.0.0""#, r#""""#, r#""0""#, r#""beta""#] {
+            let hints = ClientHints::from_headers([
+                ("Sec-CH-UA-Platform", r#""Windows""#),
+                ("Sec-CH-UA-Platform-Version", version),
+            ]);
+            let client = parser.parse_with_client_hints(user_agent, &hints);
+            assert_eq!(client.os, parser.parse_os(user_agent), "{}", version);
+            assert_eq!(client.os.major.as_deref(), Some("7"));
+        }
+    }
+
+    #[test]
+    fn model_hint_replaces_reduced_device() {
+        let parser = parser();
+        let user_agent = "Mozilla/5.0 (Linux; Android 10; K) AppleWebKit/537.36 \
+                          (KHTML, like Gecko) Chrome/118.0.0.0 Mobile Safari/537.36";
+        let hints = ClientHints::from_headers([
+            (
+                "Sec-CH-UA",
+                r#""Google Chrome";v="118", "Chromium";v="118""#,
+            ),
+            ("Sec-CH-UA-Model", r#""Pixel 7""#),
+            ("Sec-CH-UA-Mobile", "?1"),
+            ("Sec-CH-UA-Platform", r#""Android""#),
+            ("Sec-CH-UA-Platform-Version", r#""14.0.0""#),
+        ]);
+
+        let client = parser.parse_with_client_hints(user_agent, &hints);
+        assert_eq!(client.user_agent.family, "Chrome Mobile");
+        assert_eq!(client.user_agent.minor.as_deref(), Some("0"));
+        assert_eq!(client.os.family, "Android");
+        assert_eq!(client.os.major.as_deref(), Some("14"));
+        assert_eq!(client.device.family, "Pixel 7");
+        assert_eq!(client.device.brand.as_deref(), Some("Google"));
+        assert_eq!(client.device.model.as_deref(), Some("Pixel 7"));
+    }
+
+    #[test]
+    fn model_hint_keeps_device_rule_family() {
+        let parser = parser();
+        let user_agent = "Mozilla/5.0 (Linux; Android 10; K) Chrome/118.0.0.0 Mobile";
+        let hints = ClientHints::from_headers([("Sec-CH-UA-Model", r#""SM-G991B""#)]);
+        let client = parser.parse_with_client_hints(user_agent, &hints);
+        assert_eq!(
+            client.device,
+            parser.parse_device("Mozilla/5.0 (Linux; Android 10; SM-G991B Build/QP1A)")
+        );
+        assert_eq!(client.device.family, "Samsung SM-G991B");
+
+        let hints = ClientHints::from_headers([("Sec-CH-UA-Model", r#""XQ-AT51""#)]);
+        let client = parser.parse_with_client_hints(user_agent, &hints);
+        assert_eq!(client.device.family, "XQ-AT51");
+        assert_eq!(client.device.brand, None);
+        assert_eq!(client.device.model.as_deref(), Some("XQ-AT51"));
+    }
+
+    #[test]
+    fn recognizes_grease_brands() {
+        for brand in [
+            "Not=A?Brand",
+            "Not_A Brand",
+            "Not A(Brand",
+            "Not/A)Brand",
+            "Not)A;Brand",
+            " Not A;Brand",
+            " Not;A Brand",
+        ] {
+            assert!(is_grease(brand), "{}", brand);
+        }
+        for brand in [
+            "Notable Brand",
+            "Not A Brand Browser",
+            "NotABrand",
+            "Brand Not A",
+        ] {
+            assert!(!is_grease(brand), "{}", brand);
+        }
+
+        let parser = parser();
+        let hints = ClientHints::from_headers([(
+            "Sec-CH-UA",
+            r#""Chromium";v="118", "Notable Brand";v="5", "Not)A;Brand";v="24""#,
+        )]);
+        let client = parser.parse_with_client_hints("Chrome/118.0.1.2", &hints);
+        assert_eq!(client.user_agent.family, "Notable Brand");
+        assert_eq!(client.user_agent.major.as_deref(), Some("5"));
+    }
+
+    #[test]
+    fn malformed_hints_fall_back_to_user_agent() {
+        let parser = parser();
+        let user_agent = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) Chrome/118.0.1.2";
+        let hints = ClientHints {
+            brands: Some(Cow::Borrowed(r#""Google Chrome";v="118"#)),
+            platform: Some(Cow::Borrowed("Windows")),
+            ..ClientHints::default()
+        };
+
+        assert_eq!(
+            parser.parse_with_client_hints(user_agent, &hints),
+            parser.parse(user_agent)
+        );
+    }
+}
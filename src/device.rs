@@ -18,3 +18,16 @@ impl<'a> Default for Device<'a> {
         }
     }
 }
+
+impl Device<'_> {
+    /// Converts this into a `Device` that owns all of its data, so it can
+    /// outlive the user agent string it was parsed from.
+    #[must_use]
+    pub fn into_owned(self) -> Device<'static> {
+        Device {
+            family: Cow::Owned(self.family.into_owned()),
+            brand: self.brand.map(|x| Cow::Owned(x.into_owned())),
+            model: self.model.map(|x| Cow::Owned(x.into_owned())),
+        }
+    }
+}
@@ -0,0 +1,434 @@
+use super::{Client, Deserialize, Serialize};
+
+/// The form factor of the device a `Client` runs on, as returned by
+/// `Client::device_type`.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, Eq, Hash, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum DeviceType {
+    Desktop,
+    Mobile,
+    Tablet,
+    Tv,
+    Console,
+    Wearable,
+    Car,
+    /// Bots, and anything the parsed fields say too little about
+    Unknown,
+}
+
+impl DeviceType {
+    /// Returns the name this serializes as, such as `tv`.
+    #[must_use]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            DeviceType::Desktop => "desktop",
+            DeviceType::Mobile => "mobile",
+            DeviceType::Tablet => "tablet",
+            DeviceType::Tv => "tv",
+            DeviceType::Console => "console",
+            DeviceType::Wearable => "wearable",
+            DeviceType::Car => "car",
+            DeviceType::Unknown => "unknown",
+        }
+    }
+}
+
+/// The part of a `Client` a `Rule` looks at.
+#[derive(Clone, Copy)]
+enum Field {
+    /// The `Device` family and model
+    Device,
+    /// The `OS` family
+    OS,
+    /// The `UserAgent` family
+    UserAgent,
+    /// The `UserAgent` family, if the `OS` is Android
+    AndroidUserAgent,
+}
+
+/// Classifies a `Client` as `device_type` if `field` contains any of
+/// `needles` as a word, ignoring case. A needle may be followed by digits, as
+/// in `iPhone14,2`, but not by more letters.
+struct Rule {
+    field: Field,
+    needles: &'static [&'static str],
+    device_type: DeviceType,
+}
+
+/// The rules `Client::device_type` tries in order. More specific form
+/// factors come first, so a tablet running Android is not counted as mobile
+/// and a phone running Windows is not counted as a desktop.
+///
+/// Android phones are told apart from tablets by the `Mobile` token, which
+/// only phones send. Browsers that report it through their family, such as
+/// `Chrome Mobile`, run on a tablet when the family lacks it.
+static RULES: &[Rule] = &[
+    Rule {
+        field: Field::Device,
+        needles: &["playstation", "xbox", "nintendo", "wii", "ouya"],
+        device_type: DeviceType::Console,
+    },
+    Rule {
+        field: Field::OS,
+        needles: &["playstation", "xbox", "nintendo"],
+        device_type: DeviceType::Console,
+    },
+    Rule {
+        field: Field::Device,
+        needles: &[
+            "smart tv",
+            "smarttv",
+            "smart-tv",
+            "apple tv",
+            "appletv",
+            "google tv",
+            "googletv",
+            "chromecast",
+            "fire tv",
+            "firetv",
+            "roku",
+            "bravia",
+            "hbbtv",
+            "netcast",
+            "web0s",
+        ],
+        device_type: DeviceType::Tv,
+    },
+    Rule {
+        field: Field::OS,
+        needles: &["tvos", "roku", "tizen tv", "webos tv", "android tv"],
+        device_type: DeviceType::Tv,
+    },
+    Rule {
+        field: Field::Device,
+        needles: &["watch", "gear s", "glass"],
+        device_type: DeviceType::Wearable,
+    },
+    Rule {
+        field: Field::OS,
+        needles: &["watchos", "wear os", "android wear"],
+        device_type: DeviceType::Wearable,
+    },
+    Rule {
+        field: Field::Device,
+        needles: &["tesla", "carplay", "automotive"],
+        device_type: DeviceType::Car,
+    },
+    Rule {
+        field: Field::OS,
+        needles: &["android automotive", "qnx car"],
+        device_type: DeviceType::Car,
+    },
+    Rule {
+        field: Field::Device,
+        needles: &[
+            "ipad",
+            "tablet",
+            "kindle",
+            "galaxy tab",
+            "sm-t",
+            "nexus 7",
+            "nexus 9",
+            "nexus 10",
+            "pixel c",
+            "xoom",
+            "mediapad",
+            "playbook",
+        ],
+        device_type: DeviceType::Tablet,
+    },
+    Rule {
+        field: Field::Device,
+        needles: &[
+            "iphone",
+            "ipod",
+            "smartphone",
+            "feature phone",
+            "generic_android",
+        ],
+        device_type: DeviceType::Mobile,
+    },
+    Rule {
+        field: Field::UserAgent,
+        needles: &["mobile"],
+        device_type: DeviceType::Mobile,
+    },
+    Rule {
+        field: Field::AndroidUserAgent,
+        needles: &["chrome", "chromium", "firefox", "edge", "opera"],
+        device_type: DeviceType::Tablet,
+    },
+    Rule {
+        field: Field::OS,
+        needles: &[
+            "ios",
+            "android",
+            "windows phone",
+            "windows mobile",
+            "windows ce",
+            "blackberry",
+            "kaios",
+            "symbian",
+            "firefox os",
+            "sailfish",
+            "ubuntu touch",
+            "harmonyos",
+        ],
+        device_type: DeviceType::Mobile,
+    },
+    Rule {
+        field: Field::OS,
+        needles: &[
+            "windows",
+            "mac os x",
+            "macos",
+            "chrome os",
+            "chromeos",
+            "linux",
+            "ubuntu",
+            "fedora",
+            "debian",
+            "red hat",
+            "suse",
+            "mint",
+            "gentoo",
+            "freebsd",
+            "openbsd",
+            "netbsd",
+            "solaris",
+        ],
+        device_type: DeviceType::Desktop,
+    },
+];
+
+/// The fields of a `Client` the rules look at, lowercased once up front.
+struct Fields {
+    device: String,
+    model: Option<String>,
+    os: String,
+    user_agent: String,
+}
+
+impl Rule {
+    fn matches(&self, fields: &Fields) -> bool {
+        let contains = |haystack: &str| {
+            self.needles
+                .iter()
+                .any(|needle| contains_word(haystack, needle))
+        };
+
+        match self.field {
+            Field::Device => {
+                contains(&fields.device) || fields.model.as_deref().is_some_and(contains)
+            }
+            Field::OS => contains(&fields.os),
+            Field::UserAgent => contains(&fields.user_agent),
+            Field::AndroidUserAgent => {
+                contains_word(&fields.os, "android") && contains(&fields.user_agent)
+            }
+        }
+    }
+}
+
+/// Returns whether `needle` occurs in `haystack` at the start of a word and
+/// is not followed by another letter.
+fn contains_word(haystack: &str, needle: &str) -> bool {
+    haystack.match_indices(needle).any(|(at, _)| {
+        let before = haystack[..at].chars().next_back();
+        let after = haystack[at + needle.len()..].chars().next();
+        !before.is_some_and(char::is_alphanumeric)
+            && !after.is_some_and(char::is_alphabetic)
+    })
+}
+
+/// Classifies `client` by the first rule in `RULES` that matches it.
+pub(crate) fn classify(client: &Client<'_>) -> DeviceType {
+    if client.is_bot() {
+        return DeviceType::Unknown;
+    }
+
+    let fields = Fields {
+        device: client.device.family.to_ascii_lowercase(),
+        model: client.device.model.as_deref().map(str::to_ascii_lowercase),
+        os: client.os.family.to_ascii_lowercase(),
+        user_agent: client.user_agent.family.to_ascii_lowercase(),
+    };
+
+    RULES
+        .iter()
+        .find(|rule| rule.matches(&fields))
+        .map_or(DeviceType::Unknown, |rule| rule.device_type)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+
+    use super::*;
+    use crate::{Architecture, BotCategory, BotInfo, Device, Engine, UserAgent, OS};
+
+    fn client<'a>(
+        device: &'a str,
+        model: Option<&'a str>,
+        os: &'a str,
+        user_agent: &'a str,
+    ) -> Client<'a> {
+        Client {
+            device: Device {
+                family: Cow::Borrowed(device),
+                brand: None,
+                model: model.map(Cow::Borrowed),
+            },
+            os: OS {
+                family: Cow::Borrowed(os),
+                ..OS::default()
+            },
+            user_agent: UserAgent {
+                family: Cow::Borrowed(user_agent),
+                ..UserAgent::default()
+            },
+            engine: Engine::default(),
+            architecture: Architecture::default(),
+            bot: None,
+        }
+    }
+
+    #[test]
+    fn classifies_form_factors() {
+        for (client, expected) in [
+            (
+                client("Other", None, "Windows", "Chrome"),
+                DeviceType::Desktop,
+            ),
+            (
+                client("Mac", Some("Mac"), "Mac OS X", "Safari"),
+                DeviceType::Desktop,
+            ),
+            (
+                client("Other", None, "Ubuntu", "Firefox"),
+                DeviceType::Desktop,
+            ),
+            (
+                client("iPhone", Some("iPhone"), "iOS", "Mobile Safari"),
+                DeviceType::Mobile,
+            ),
+            (
+                client("iPad", Some("iPad"), "iOS", "Mobile Safari"),
+                DeviceType::Tablet,
+            ),
+            (
+                client("Samsung SM-T510", Some("SM-T510"), "Android", "Chrome"),
+                DeviceType::Tablet,
+            ),
+            (
+                client(
+                    "Samsung SM-S918B",
+                    Some("SM-S918B"),
+                    "Android",
+                    "Chrome Mobile",
+                ),
+                DeviceType::Mobile,
+            ),
+            (
+                client("Other", None, "Windows Phone", "IE Mobile"),
+                DeviceType::Mobile,
+            ),
+            (
+                client("PlayStation 5", Some("PlayStation 5"), "Other", "Other"),
+                DeviceType::Console,
+            ),
+            (
+                client("Xbox One", None, "Windows", "Edge"),
+                DeviceType::Console,
+            ),
+            (
+                client("Generic Smart TV", None, "Linux", "Chrome"),
+                DeviceType::Tv,
+            ),
+            (
+                client("AppleTV", Some("AppleTV11,1"), "tvOS", "Other"),
+                DeviceType::Tv,
+            ),
+            (
+                client("Apple Watch", None, "watchOS", "Other"),
+                DeviceType::Wearable,
+            ),
+            (client("Tesla", None, "Linux", "Chrome"), DeviceType::Car),
+            (client("Other", None, "Other", "curl"), DeviceType::Unknown),
+        ] {
+            assert_eq!(client.device_type(), expected, "{client:?}");
+        }
+    }
+
+    #[test]
+    fn tells_android_tablets_by_missing_mobile_token() {
+        for (client, expected) in [
+            (
+                client("Lenovo TB-X606F", Some("TB-X606F"), "Android", "Chrome"),
+                DeviceType::Tablet,
+            ),
+            (
+                client("Other", None, "Android", "Firefox"),
+                DeviceType::Tablet,
+            ),
+            (
+                client("Other", None, "Android", "Chrome Mobile"),
+                DeviceType::Mobile,
+            ),
+            (
+                client("Other", None, "Android", "Chrome Mobile WebView"),
+                DeviceType::Mobile,
+            ),
+            (
+                client("Generic Smartphone", None, "Android", "Chrome"),
+                DeviceType::Mobile,
+            ),
+            (
+                client(
+                    "Samsung SM-G991B",
+                    Some("SM-G991B"),
+                    "Android",
+                    "Samsung Internet",
+                ),
+                DeviceType::Mobile,
+            ),
+        ] {
+            assert_eq!(client.device_type(), expected, "{client:?}");
+        }
+    }
+
+    #[test]
+    fn matches_whole_words() {
+        for (client, expected) in [
+            (
+                client("Galaxy Watch4", Some("SM-R870"), "Wear OS", "Other"),
+                DeviceType::Wearable,
+            ),
+            (
+                client("iPhone", Some("iPhone14,2"), "iOS", "Mobile Safari"),
+                DeviceType::Mobile,
+            ),
+            (
+                client("Glassbox", None, "Linux", "Chrome"),
+                DeviceType::Desktop,
+            ),
+            (
+                client("WatchGuard", None, "Windows", "Firefox"),
+                DeviceType::Desktop,
+            ),
+        ] {
+            assert_eq!(client.device_type(), expected, "{client:?}");
+        }
+    }
+
+    #[test]
+    fn bots_have_no_form_factor() {
+        let mut client = client("Spider", Some("Desktop"), "Windows", "Chrome");
+        client.bot = Some(BotInfo {
+            name: Cow::Borrowed("Googlebot"),
+            category: BotCategory::SearchEngine,
+            vendor: None,
+        });
+        assert_eq!(client.device_type(), DeviceType::Unknown);
+    }
+}
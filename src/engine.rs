@@ -0,0 +1,50 @@
+use std::borrow::Cow;
+
+use super::{Deserialize, Serialize, Version};
+
+/// Describes the `Family` as well as the `Major`, `Minor`, and `Patch` versions
+/// of the browser `Engine` that renders pages, such as Blink or Gecko
+#[derive(Clone, Debug, Deserialize, Serialize, Eq, Hash, PartialEq)]
+pub struct Engine<'a> {
+    pub family: Cow<'a, str>,
+    pub major: Option<Cow<'a, str>>,
+    pub minor: Option<Cow<'a, str>>,
+    pub patch: Option<Cow<'a, str>>,
+}
+
+impl Default for Engine<'_> {
+    fn default() -> Self {
+        Self {
+            family: Cow::Borrowed("Other"),
+            major: None,
+            minor: None,
+            patch: None,
+        }
+    }
+}
+
+impl Engine<'_> {
+    /// Returns the version of this `Engine`, or `None` if no major version was
+    /// found.
+    #[must_use]
+    pub fn version(&self) -> Option<Version<'_>> {
+        Some(Version {
+            major: Cow::Borrowed(self.major.as_deref()?),
+            minor: self.minor.as_deref().map(Cow::Borrowed),
+            patch: self.patch.as_deref().map(Cow::Borrowed),
+            patch_minor: None,
+        })
+    }
+
+    /// Converts this into an `Engine` that owns all of its data, so it can
+    /// outlive the user agent string it was parsed from.
+    #[must_use]
+    pub fn into_owned(self) -> Engine<'static> {
+        Engine {
+            family: Cow::Owned(self.family.into_owned()),
+            major: self.major.map(|x| Cow::Owned(x.into_owned())),
+            minor: self.minor.map(|x| Cow::Owned(x.into_owned())),
+            patch: self.patch.map(|x| Cow::Owned(x.into_owned())),
+        }
+    }
+}
@@ -0,0 +1,41 @@
+use std::sync::Arc;
+
+use super::{Client, Serialize};
+
+/// Describes how a `UserAgentParser` arrived at its result, as returned by
+/// `UserAgentParser::explain`.
+#[derive(Clone, Debug, Serialize, Eq, PartialEq)]
+pub struct Explanation<'a> {
+    pub client: Client<'a>,
+    pub device: Option<RuleMatch<'a>>,
+    pub os: Option<RuleMatch<'a>>,
+    pub user_agent: Option<RuleMatch<'a>>,
+    pub engine: Option<RuleMatch<'a>>,
+    pub architecture: Option<RuleMatch<'a>>,
+    pub bot: Option<RuleMatch<'a>>,
+}
+
+/// The rule that produced part of a `Client`.
+#[derive(Clone, Debug, Serialize, Eq, PartialEq)]
+pub struct RuleMatch<'a> {
+    /// The name of the overlay, or bundled rule file such as `bots.yaml`, that
+    /// defined the rule, or `None` if it came from the base `regexes.yaml`
+    pub file: Option<Arc<str>>,
+    /// The position of the rule within its section of that file
+    pub index: usize,
+    /// The pattern the rule was compiled from, after any flags were applied
+    pub regex: String,
+    /// Every capture group of the match, starting with the whole match
+    pub captures: Vec<Option<&'a str>>,
+    /// The replacements the rule defines, in place of capture groups
+    pub replacements: Vec<Replacement>,
+}
+
+/// A replacement template from a rule, along with the value it produced.
+#[derive(Clone, Debug, Serialize, Eq, PartialEq)]
+pub struct Replacement {
+    /// The field of the result the replacement applies to, such as `family`
+    pub field: &'static str,
+    pub template: String,
+    pub value: Option<String>,
+}
@@ -1,14 +1,111 @@
 use super::*;
 
-#[derive(Debug, Deserialize)]
+/// The rules a `UserAgentParser` is built from, usually deserialized from
+/// `regexes.yaml`. Rules can also be defined in code:
+///
+/// ```rust
+/// # use uaparser::*;
+/// let regex_file = RegexFile::default()
+///     .user_agent(UserAgentRule::new(r"(InternalApp)/(\d+)\.(\d+)").family("Internal $1"))
+///     .os(OSRule::new(r"Windows NT 10\.0").family("Windows").v1("10"))
+///     .device(DeviceRule::new(r"; *(SM-\w+)").regex_flag("i").brand("Samsung").model("$1"));
+///
+/// let parser = UserAgentParser::try_from(regex_file).unwrap();
+/// let client = parser.parse("InternalApp/3.1 (Windows NT 10.0; SM-T510)");
+/// assert_eq!(client.user_agent.family, "Internal InternalApp");
+/// assert_eq!(client.os.family, "Windows");
+/// assert_eq!(client.device.model.as_deref(), Some("SM-T510"));
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+#[allow(clippy::struct_field_names)]
 pub struct RegexFile {
+    /// Rules for parsing the `UserAgent`, tried in order
     pub user_agent_parsers: Vec<UserAgentParserEntry>,
+    /// Rules for parsing the `OS`, tried in order
     pub os_parsers: Vec<OSParserEntry>,
+    /// Rules for parsing the `Device`, tried in order
     pub device_parsers: Vec<DeviceParserEntry>,
+    /// Rules for detecting the browser engine. Upstream `regexes.yaml` has no
+    /// such section, so this is empty unless a file or overlay provides one.
+    #[serde(default)]
+    pub engine_parsers: Vec<EngineParserEntry>,
+    /// Rules for detecting the CPU architecture. Upstream `regexes.yaml` has
+    /// no such section, so this is empty unless a file or overlay provides one.
+    #[serde(default)]
+    pub architecture_parsers: Vec<ArchitectureParserEntry>,
+    /// Rules for classifying automated agents. Upstream `regexes.yaml` has no
+    /// such section, so this is empty unless a file or overlay provides one.
+    #[serde(default)]
+    pub bot_parsers: Vec<BotParserEntry>,
 }
 
-#[derive(Debug, Deserialize)]
+impl RegexFile {
+    /// Adds a rule to the end of `user_agent_parsers`.
+    #[must_use]
+    pub fn user_agent(mut self, rule: UserAgentParserEntry) -> Self {
+        self.user_agent_parsers.push(rule);
+        self
+    }
+
+    /// Adds a rule to the end of `os_parsers`.
+    #[must_use]
+    pub fn os(mut self, rule: OSParserEntry) -> Self {
+        self.os_parsers.push(rule);
+        self
+    }
+
+    /// Adds a rule to the end of `device_parsers`.
+    #[must_use]
+    pub fn device(mut self, rule: DeviceParserEntry) -> Self {
+        self.device_parsers.push(rule);
+        self
+    }
+
+    /// Adds a rule to the end of `engine_parsers`.
+    #[must_use]
+    pub fn engine(mut self, rule: EngineParserEntry) -> Self {
+        self.engine_parsers.push(rule);
+        self
+    }
+
+    /// Adds a rule to the end of `architecture_parsers`.
+    #[must_use]
+    pub fn architecture(mut self, rule: ArchitectureParserEntry) -> Self {
+        self.architecture_parsers.push(rule);
+        self
+    }
+
+    /// Adds a rule to the end of `bot_parsers`.
+    #[must_use]
+    pub fn bot(mut self, rule: BotParserEntry) -> Self {
+        self.bot_parsers.push(rule);
+        self
+    }
+}
+
+/// A rule for parsing the `UserAgent`, as built in code.
+pub type UserAgentRule = UserAgentParserEntry;
+
+/// A rule for parsing the `OS`, as built in code.
+pub type OSRule = OSParserEntry;
+
+/// A rule for parsing the `Device`, as built in code.
+pub type DeviceRule = DeviceParserEntry;
+
+/// A rule for detecting the `Engine`, as built in code.
+pub type EngineRule = EngineParserEntry;
+
+/// A rule for detecting the `Architecture`, as built in code.
+pub type ArchitectureRule = ArchitectureParserEntry;
+
+/// A rule for classifying bots, as built in code.
+pub type BotRule = BotParserEntry;
+
+#[derive(Debug, Clone, Deserialize)]
+#[non_exhaustive]
 pub struct UserAgentParserEntry {
+    /// An optional identifier, used to replace this rule from an overlay
+    pub id: Option<String>,
     pub regex: String,
     pub family_replacement: Option<String>,
     pub v1_replacement: Option<String>,
@@ -16,20 +113,342 @@ pub struct UserAgentParserEntry {
     pub v3_replacement: Option<String>,
 }
 
-#[derive(Debug, Deserialize)]
+impl UserAgentParserEntry {
+    /// Creates a rule that takes every field from the groups of `regex`.
+    #[must_use]
+    pub fn new(regex: impl Into<String>) -> Self {
+        UserAgentParserEntry {
+            id: None,
+            regex: regex.into(),
+            family_replacement: None,
+            v1_replacement: None,
+            v2_replacement: None,
+            v3_replacement: None,
+        }
+    }
+
+    /// Sets the `id` an overlay can replace this rule by.
+    #[must_use]
+    pub fn id(mut self, id: impl Into<String>) -> Self {
+        self.id = Some(id.into());
+        self
+    }
+
+    /// Sets the template for `UserAgent::family`.
+    #[must_use]
+    pub fn family(mut self, replacement: impl Into<String>) -> Self {
+        self.family_replacement = Some(replacement.into());
+        self
+    }
+
+    /// Sets the template for `UserAgent::major`.
+    #[must_use]
+    pub fn v1(mut self, replacement: impl Into<String>) -> Self {
+        self.v1_replacement = Some(replacement.into());
+        self
+    }
+
+    /// Sets the template for `UserAgent::minor`.
+    #[must_use]
+    pub fn v2(mut self, replacement: impl Into<String>) -> Self {
+        self.v2_replacement = Some(replacement.into());
+        self
+    }
+
+    /// Sets the template for `UserAgent::patch`.
+    #[must_use]
+    pub fn v3(mut self, replacement: impl Into<String>) -> Self {
+        self.v3_replacement = Some(replacement.into());
+        self
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[non_exhaustive]
 pub struct OSParserEntry {
+    /// An optional identifier, used to replace this rule from an overlay
+    pub id: Option<String>,
     pub regex: String,
     pub os_replacement: Option<String>,
     pub os_v1_replacement: Option<String>,
     pub os_v2_replacement: Option<String>,
     pub os_v3_replacement: Option<String>,
+    /// Fills in `OS::patch_minor`
+    #[serde(default)]
+    pub os_v4_replacement: Option<String>,
+}
+
+impl OSParserEntry {
+    /// Creates a rule that takes every field from the groups of `regex`.
+    #[must_use]
+    pub fn new(regex: impl Into<String>) -> Self {
+        OSParserEntry {
+            id: None,
+            regex: regex.into(),
+            os_replacement: None,
+            os_v1_replacement: None,
+            os_v2_replacement: None,
+            os_v3_replacement: None,
+            os_v4_replacement: None,
+        }
+    }
+
+    /// Sets the `id` an overlay can replace this rule by.
+    #[must_use]
+    pub fn id(mut self, id: impl Into<String>) -> Self {
+        self.id = Some(id.into());
+        self
+    }
+
+    /// Sets the template for `OS::family`.
+    #[must_use]
+    pub fn family(mut self, replacement: impl Into<String>) -> Self {
+        self.os_replacement = Some(replacement.into());
+        self
+    }
+
+    /// Sets the template for `OS::major`.
+    #[must_use]
+    pub fn v1(mut self, replacement: impl Into<String>) -> Self {
+        self.os_v1_replacement = Some(replacement.into());
+        self
+    }
+
+    /// Sets the template for `OS::minor`.
+    #[must_use]
+    pub fn v2(mut self, replacement: impl Into<String>) -> Self {
+        self.os_v2_replacement = Some(replacement.into());
+        self
+    }
+
+    /// Sets the template for `OS::patch`.
+    #[must_use]
+    pub fn v3(mut self, replacement: impl Into<String>) -> Self {
+        self.os_v3_replacement = Some(replacement.into());
+        self
+    }
+
+    /// Sets the template for `OS::patch_minor`.
+    #[must_use]
+    pub fn v4(mut self, replacement: impl Into<String>) -> Self {
+        self.os_v4_replacement = Some(replacement.into());
+        self
+    }
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
+#[non_exhaustive]
 pub struct DeviceParserEntry {
+    /// An optional identifier, used to replace this rule from an overlay
+    pub id: Option<String>,
     pub regex_flag: Option<String>,
     pub regex: String,
     pub device_replacement: Option<String>,
     pub brand_replacement: Option<String>,
     pub model_replacement: Option<String>,
 }
+
+impl DeviceParserEntry {
+    /// Creates a rule that takes every field from the groups of `regex`.
+    #[must_use]
+    pub fn new(regex: impl Into<String>) -> Self {
+        DeviceParserEntry {
+            id: None,
+            regex_flag: None,
+            regex: regex.into(),
+            device_replacement: None,
+            brand_replacement: None,
+            model_replacement: None,
+        }
+    }
+
+    /// Sets the `id` an overlay can replace this rule by.
+    #[must_use]
+    pub fn id(mut self, id: impl Into<String>) -> Self {
+        self.id = Some(id.into());
+        self
+    }
+
+    /// Sets inline flags for `regex`, such as `i` for case insensitivity.
+    #[must_use]
+    pub fn regex_flag(mut self, flag: impl Into<String>) -> Self {
+        self.regex_flag = Some(flag.into());
+        self
+    }
+
+    /// Sets the template for `Device::family`.
+    #[must_use]
+    pub fn family(mut self, replacement: impl Into<String>) -> Self {
+        self.device_replacement = Some(replacement.into());
+        self
+    }
+
+    /// Sets the template for `Device::brand`.
+    #[must_use]
+    pub fn brand(mut self, replacement: impl Into<String>) -> Self {
+        self.brand_replacement = Some(replacement.into());
+        self
+    }
+
+    /// Sets the template for `Device::model`.
+    #[must_use]
+    pub fn model(mut self, replacement: impl Into<String>) -> Self {
+        self.model_replacement = Some(replacement.into());
+        self
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[non_exhaustive]
+pub struct EngineParserEntry {
+    /// An optional identifier, used to replace this rule from an overlay
+    pub id: Option<String>,
+    pub regex: String,
+    pub family_replacement: Option<String>,
+    pub v1_replacement: Option<String>,
+    pub v2_replacement: Option<String>,
+    pub v3_replacement: Option<String>,
+}
+
+impl EngineParserEntry {
+    /// Creates a rule that takes every field from the groups of `regex`.
+    #[must_use]
+    pub fn new(regex: impl Into<String>) -> Self {
+        EngineParserEntry {
+            id: None,
+            regex: regex.into(),
+            family_replacement: None,
+            v1_replacement: None,
+            v2_replacement: None,
+            v3_replacement: None,
+        }
+    }
+
+    /// Sets the `id` an overlay can replace this rule by.
+    #[must_use]
+    pub fn id(mut self, id: impl Into<String>) -> Self {
+        self.id = Some(id.into());
+        self
+    }
+
+    /// Sets the template for `Engine::family`.
+    #[must_use]
+    pub fn family(mut self, replacement: impl Into<String>) -> Self {
+        self.family_replacement = Some(replacement.into());
+        self
+    }
+
+    /// Sets the template for `Engine::major`.
+    #[must_use]
+    pub fn v1(mut self, replacement: impl Into<String>) -> Self {
+        self.v1_replacement = Some(replacement.into());
+        self
+    }
+
+    /// Sets the template for `Engine::minor`.
+    #[must_use]
+    pub fn v2(mut self, replacement: impl Into<String>) -> Self {
+        self.v2_replacement = Some(replacement.into());
+        self
+    }
+
+    /// Sets the template for `Engine::patch`.
+    #[must_use]
+    pub fn v3(mut self, replacement: impl Into<String>) -> Self {
+        self.v3_replacement = Some(replacement.into());
+        self
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[non_exhaustive]
+pub struct ArchitectureParserEntry {
+    /// An optional identifier, used to replace this rule from an overlay
+    pub id: Option<String>,
+    pub regex: String,
+    pub family_replacement: Option<String>,
+    /// Whether a match means a 32-bit agent running on a 64-bit OS
+    #[serde(default)]
+    pub emulated: bool,
+}
+
+impl ArchitectureParserEntry {
+    /// Creates a rule that takes the family from the first group of `regex`.
+    #[must_use]
+    pub fn new(regex: impl Into<String>) -> Self {
+        ArchitectureParserEntry {
+            id: None,
+            regex: regex.into(),
+            family_replacement: None,
+            emulated: false,
+        }
+    }
+
+    /// Sets the `id` an overlay can replace this rule by.
+    #[must_use]
+    pub fn id(mut self, id: impl Into<String>) -> Self {
+        self.id = Some(id.into());
+        self
+    }
+
+    /// Sets the template for `Architecture::family`.
+    #[must_use]
+    pub fn family(mut self, replacement: impl Into<String>) -> Self {
+        self.family_replacement = Some(replacement.into());
+        self
+    }
+
+    /// Marks a match as a 32-bit agent running on a 64-bit OS.
+    #[must_use]
+    pub fn emulated(mut self, emulated: bool) -> Self {
+        self.emulated = emulated;
+        self
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[non_exhaustive]
+pub struct BotParserEntry {
+    /// An optional identifier, used to replace this rule from an overlay
+    pub id: Option<String>,
+    pub regex: String,
+    pub name_replacement: Option<String>,
+    pub category: BotCategory,
+    pub vendor: Option<String>,
+}
+
+impl BotParserEntry {
+    /// Creates a rule that takes the bot's name from the first group of
+    /// `regex`.
+    #[must_use]
+    pub fn new(regex: impl Into<String>, category: BotCategory) -> Self {
+        BotParserEntry {
+            id: None,
+            regex: regex.into(),
+            name_replacement: None,
+            category,
+            vendor: None,
+        }
+    }
+
+    /// Sets the `id` an overlay can replace this rule by.
+    #[must_use]
+    pub fn id(mut self, id: impl Into<String>) -> Self {
+        self.id = Some(id.into());
+        self
+    }
+
+    /// Sets the template for `BotInfo::name`.
+    #[must_use]
+    pub fn name(mut self, replacement: impl Into<String>) -> Self {
+        self.name_replacement = Some(replacement.into());
+        self
+    }
+
+    /// Sets `BotInfo::vendor`.
+    #[must_use]
+    pub fn vendor(mut self, vendor: impl Into<String>) -> Self {
+        self.vendor = Some(vendor.into());
+        self
+    }
+}
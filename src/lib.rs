@@ -35,29 +35,96 @@
 //! assert_eq!(client.os, os);
 //! assert_eq!(client.user_agent, user_agent);
 //! ```
+//!
+//! With the `embedded-regexes` feature enabled the crate ships with its own
+//! copy of `regexes.yaml`, so no file has to be deployed alongside it:
+//! ```rust
+//! # #[cfg(feature = "embedded-regexes")]
+//! # {
+//! # use uaparser::*;
+//! let ua_parser = UserAgentParser::default_embedded();
+//! println!("Using uap-core {}", UAP_CORE_VERSION);
+//! # }
+//! ```
 
 #![deny(clippy::all)]
 #![deny(clippy::pedantic)]
 #![allow(clippy::missing_errors_doc)]
 #![allow(clippy::wildcard_imports)]
 #![allow(clippy::module_name_repetitions)]
+// Lints newer than the code they flag, which is kept as upstream wrote it.
+#![allow(clippy::elidable_lifetime_names, clippy::unnecessary_map_or)]
+#![cfg_attr(
+    test,
+    allow(
+        clippy::explicit_iter_loop,
+        clippy::items_after_statements,
+        clippy::needless_borrow,
+        clippy::semicolon_if_nothing_returned,
+        clippy::uninlined_format_args
+    )
+)]
 
 use serde_derive::{Deserialize, Serialize};
 
+#[cfg(feature = "access-log")]
+pub mod access_log;
+
+mod architecture;
+pub use architecture::Architecture;
+
+mod bot;
+pub use bot::{BotCategory, BotInfo};
+
+mod cache;
+pub use cache::CachingParser;
+
 mod client;
+mod client_hints;
+pub use client_hints::ClientHints;
+
 mod device;
 pub use device::Device;
 
+mod device_type;
+pub use device_type::DeviceType;
+
+mod engine;
+pub use engine::Engine;
+
 mod os;
 pub use os::OS;
 
+mod reload;
+pub use reload::{ReloadableParser, Watcher};
+
+mod stats;
+pub use stats::{Dimension, Report, Share, UsageStats};
+
 mod user_agent;
 pub use user_agent::UserAgent;
 
+mod version;
+pub use version::Version;
+
+mod explain;
+pub use explain::{Explanation, Replacement, RuleMatch};
+
 mod file;
 mod parser;
 
-pub use parser::{Error, UserAgentParser};
+pub use file::{
+    ArchitectureParserEntry, ArchitectureRule, BotParserEntry, BotRule,
+    DeviceParserEntry, DeviceRule, EngineParserEntry, EngineRule, OSParserEntry, OSRule,
+    RegexFile, UserAgentParserEntry, UserAgentRule,
+};
+pub use parser::{
+    codegen, Error, Location, OverlayError, Precedence, RuleError, Section,
+    UserAgentParser, UserAgentParserBuilder,
+};
+
+#[cfg(feature = "embedded-regexes")]
+pub use parser::UAP_CORE_VERSION;
 
 pub use client::Client;
 
@@ -66,6 +133,33 @@ pub trait Parser {
     fn parse_device<'a>(&self, user_agent: &'a str) -> Device<'a>;
     fn parse_os<'a>(&self, user_agent: &'a str) -> OS<'a>;
     fn parse_user_agent<'a>(&self, user_agent: &'a str) -> UserAgent<'a>;
+
+    /// Returns just the `Engine` info when given a user agent string
+    fn parse_engine<'a>(&self, user_agent: &'a str) -> Engine<'a> {
+        self.parse(user_agent).engine
+    }
+
+    /// Returns just the `Architecture` info when given a user agent string
+    fn parse_architecture<'a>(&self, user_agent: &'a str) -> Architecture<'a> {
+        self.parse(user_agent).architecture
+    }
+
+    /// Returns just the `BotInfo` when given a user agent string, or `None` if
+    /// it does not belong to an automated agent.
+    fn parse_bot<'a>(&self, user_agent: &'a str) -> Option<BotInfo<'a>> {
+        self.parse(user_agent).bot
+    }
+
+    /// Returns the full `Client` info for a user agent string, refined by the
+    /// values of the User-Agent Client Hints headers sent along with it. See
+    /// `ClientHints` for which hint takes precedence over which field.
+    fn parse_with_client_hints<'a>(
+        &self,
+        user_agent: &'a str,
+        hints: &ClientHints<'_>,
+    ) -> Client<'a> {
+        client_hints::merge(self, self.parse(user_agent), hints)
+    }
 }
 
 pub(crate) trait SubParser<'a> {
@@ -333,6 +427,79 @@ mod tests {
         }
     }
 
+    #[cfg(feature = "embedded-regexes")]
+    #[test]
+    fn parse_with_embedded_regexes() {
+        assert_ne!(UAP_CORE_VERSION, "unknown");
+
+        let parser = UserAgentParser::default_embedded();
+        do_parse_user_agent_test_with_parser(&parser);
+        do_parse_os_test_with_parser(&parser);
+        do_parse_device_test_with_parser(&parser);
+    }
+
+    #[test]
+    fn owned_client_outlives_input() {
+        let parser = UserAgentParser::from_bytes(
+            br"
+user_agent_parsers:
+  - regex: '(Firefox)/(\d+)\.(\d+)'
+os_parsers:
+  - regex: '(Windows NT) (\d+)\.(\d+)'
+    os_replacement: 'Windows'
+device_parsers: []
+",
+        )
+        .expect("Parser creation failed");
+
+        let client: Client<'static> = {
+            let user_agent_string = String::from(
+                "Mozilla/5.0 (Windows NT 10.0; Win64; x64; rv:109.0) Firefox/115.0",
+            );
+            parser.parse(&user_agent_string).into_owned()
+        };
+
+        assert_eq!(client.user_agent.family, "Firefox");
+        assert_eq!(client.user_agent.major.as_deref(), Some("115"));
+        assert_eq!(client.os.family, "Windows");
+        assert!(client.user_agent.version() >= Some(Version::parse("110")));
+        assert_eq!(client.os.version().unwrap().to_string(), "10.0");
+
+        let handle = std::thread::spawn(move || {
+            let yaml = serde_yaml::to_string(&client).expect("Failed to serialize");
+            let round_trip: Client<'static> =
+                serde_yaml::from_str(&yaml).expect("Failed to deserialize");
+            assert_eq!(round_trip, client);
+        });
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn rules_defined_in_code() {
+        let base = RegexFile::default()
+            .user_agent(UserAgentRule::new(r"(Firefox)/(\d+)\.(\d+)").id("firefox"))
+            .os(OSRule::new(r"(Windows NT) (\d+)\.(\d+)").family("Windows"));
+        let overlay = RegexFile::default().user_agent(
+            UserAgentRule::new(r"Firefox/(\d+)")
+                .id("firefox")
+                .family("Firefox Desktop")
+                .v1("$1"),
+        );
+
+        let parser = UserAgentParser::builder()
+            .with_device(false)
+            .with_overlay("feature-flag", overlay, Precedence::ReplaceById)
+            .build_from_regex_file(base)
+            .expect("Parser creation failed");
+
+        let client =
+            parser.parse("Mozilla/5.0 (Windows NT 10.0; Win64; x64) Firefox/115.0");
+        assert_eq!(client.user_agent.family, "Firefox Desktop");
+        assert_eq!(client.os.family, "Windows");
+        assert_eq!(client.os.major.as_deref(), Some("10"));
+        assert_eq!(client.device.family, "Other");
+    }
+
     fn print_failure<T: Debug, F: Debug>(got: &T, expected: &F) {
         println!(
             r" --- Failed Test Case ----
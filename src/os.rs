@@ -1,6 +1,6 @@
 use std::borrow::Cow;
 
-use super::{Deserialize, Serialize};
+use super::{Deserialize, Serialize, Version};
 
 /// Describes the `Family` as well as the `Major`, `Minor`, `Patch`, and
 /// `PatchMinor` versions of an `OS`
@@ -24,3 +24,30 @@ impl<'a> Default for OS<'a> {
         }
     }
 }
+
+impl OS<'_> {
+    /// Returns the version of this `OS`, or `None` if no major version was
+    /// found.
+    #[must_use]
+    pub fn version(&self) -> Option<Version<'_>> {
+        Some(Version {
+            major: Cow::Borrowed(self.major.as_deref()?),
+            minor: self.minor.as_deref().map(Cow::Borrowed),
+            patch: self.patch.as_deref().map(Cow::Borrowed),
+            patch_minor: self.patch_minor.as_deref().map(Cow::Borrowed),
+        })
+    }
+
+    /// Converts this into an `OS` that owns all of its data, so it can outlive
+    /// the user agent string it was parsed from.
+    #[must_use]
+    pub fn into_owned(self) -> OS<'static> {
+        OS {
+            family: Cow::Owned(self.family.into_owned()),
+            major: self.major.map(|x| Cow::Owned(x.into_owned())),
+            minor: self.minor.map(|x| Cow::Owned(x.into_owned())),
+            patch: self.patch.map(|x| Cow::Owned(x.into_owned())),
+            patch_minor: self.patch_minor.map(|x| Cow::Owned(x.into_owned())),
+        }
+    }
+}
@@ -0,0 +1,176 @@
+use serde_derive::Deserialize;
+
+use super::*;
+
+#[derive(Debug, Display, From)]
+pub enum Error {
+    Regex(regex::Error),
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Regex(e) => e.source(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Matcher {
+    regex: LazyRegex,
+    family_replacement: Option<Template>,
+    emulated: bool,
+}
+
+impl<'a> SubParser<'a> for Matcher {
+    type Item = Architecture<'a>;
+
+    fn try_parse(&self, text: &'a str) -> Option<Self::Item> {
+        let captures = self.regex.get()?.captures(text.as_bytes())?;
+
+        let family: Cow<'a, str> = match &self.family_replacement {
+            Some(family_replacement) => family_replacement.expand(&captures),
+            None => template::capture(&captures, 1)?,
+        };
+
+        Some(Architecture {
+            family,
+            emulated: self.emulated,
+        })
+    }
+}
+
+impl Pattern for Matcher {
+    fn regex(&self) -> &LazyRegex {
+        &self.regex
+    }
+}
+
+impl Explain<'_> for Matcher {
+    fn replacements(&self, item: &Architecture<'_>) -> Vec<Replacement> {
+        replacement(
+            "family",
+            self.family_replacement.as_ref(),
+            Some(&item.family),
+        )
+        .into_iter()
+        .collect()
+    }
+}
+
+impl Matcher {
+    pub fn try_from(
+        entry: ArchitectureParserEntry,
+        unicode: bool,
+        lazy: bool,
+    ) -> Result<Matcher, Error> {
+        let regex = LazyRegex::new(
+            clean_escapes(&entry.regex).into_owned(),
+            unicode,
+            None,
+            lazy,
+        )?;
+
+        Ok(Matcher {
+            regex,
+            family_replacement: entry.family_replacement.map(Template::new),
+            emulated: entry.emulated,
+        })
+    }
+
+    pub fn from_precompiled(
+        entry: &PrecompiledArchitectureEntry,
+        unicode: bool,
+        lazy: bool,
+    ) -> Result<Matcher, Error> {
+        Ok(Matcher {
+            regex: LazyRegex::new(entry.regex.to_owned(), unicode, None, lazy)?,
+            family_replacement: entry.family_replacement.map(Template::from),
+            emulated: entry.emulated,
+        })
+    }
+}
+
+/// The rules from the `architectures.yaml` bundled with this crate.
+pub(super) fn builtin_rules() -> Vec<ArchitectureParserEntry> {
+    #[derive(Deserialize)]
+    struct ArchitecturesFile {
+        architecture_parsers: Vec<ArchitectureParserEntry>,
+    }
+
+    let file: ArchitecturesFile = serde_yaml::from_slice(BUILTIN_ARCHITECTURES)
+        .expect("bundled architectures.yaml should always be valid");
+    file.architecture_parsers
+}
+
+static BUILTIN_ARCHITECTURES: &[u8] = include_bytes!("../architectures.yaml");
+
+/// The overlay name the bundled architecture rules are reported under.
+pub(super) const BUILTIN_NAME: &str = "architectures.yaml";
+
+#[cfg(test)]
+mod tests {
+    use crate::{ArchitectureRule, Parser, RegexFile, UserAgentParser};
+
+    fn parser() -> UserAgentParser {
+        UserAgentParser::builder()
+            .with_architecture(true)
+            .build_from_regex_file(RegexFile::default())
+            .expect("Parser creation failed")
+    }
+
+    #[test]
+    fn detects_architectures() {
+        let parser = parser();
+        for (ua, family, emulated) in [
+            ("Mozilla/5.0 (X11; Linux x86_64; rv:109.0) Gecko/20100101 Firefox/115.0", "x86_64", false),
+            ("Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36", "x86_64", false),
+            ("Mozilla/5.0 (Windows NT 6.1; WOW64; Trident/7.0; rv:11.0) like Gecko", "x86_64", true),
+            ("Mozilla/5.0 (X11; Linux i686 on x86_64; rv:109.0) Gecko/20100101 Firefox/115.0", "x86_64", true),
+            ("Mozilla/5.0 (X11; Linux i686; rv:109.0) Gecko/20100101 Firefox/115.0", "x86", false),
+            ("Mozilla/5.0 (X11; Linux aarch64) AppleWebKit/537.36", "arm64", false),
+            ("Mozilla/5.0 (X11; CrOS armv7l 13597.84.0) AppleWebKit/537.36", "arm", false),
+            ("Mozilla/5.0 (Windows NT 10.0; ARM; Lumia 950) AppleWebKit/537.36", "arm", false),
+            ("Mozilla/5.0 (Macintosh; U; PPC Mac OS X; en) AppleWebKit/125.2", "ppc", false),
+            ("Mozilla/5.0 (X11; Linux ppc64le; rv:109.0) Gecko/20100101 Firefox/115.0", "ppc64", false),
+            ("Mozilla/5.0 (X11; U; Linux ppc; en-US; rv:1.9.0.3) Gecko/2008092814", "ppc", false),
+            ("Mozilla/4.0 (compatible; MSIE 5.23; Mac_PowerPC)", "ppc", false),
+        ] {
+            let architecture = parser.parse_architecture(ua);
+            assert_eq!(architecture.family, family, "{ua}");
+            assert_eq!(architecture.emulated, emulated, "{ua}");
+            assert_eq!(parser.parse(ua).architecture, architecture);
+        }
+
+        let architecture = parser.parse_architecture(
+            "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/605.1.15",
+        );
+        assert_eq!(architecture.family, "Other");
+
+        let architecture = parser.parse_architecture(
+            "Mozilla/4.0 (compatible; MSIE 4.01; Windows CE; PPC; 240x320)",
+        );
+        assert_eq!(architecture.family, "Other");
+    }
+
+    #[test]
+    fn rules_from_the_regexes_file_come_first() {
+        let regex_file = RegexFile::default()
+            .architecture(ArchitectureRule::new(r"\b(loongarch64)\b"))
+            .architecture(ArchitectureRule::new(r"\bi86pc\b").family("x86"));
+        let parser = UserAgentParser::builder()
+            .with_architecture(true)
+            .build_from_regex_file(regex_file)
+            .expect("Parser creation failed");
+
+        let architecture =
+            parser.parse_architecture("Mozilla/5.0 (X11; Linux loongarch64)");
+        assert_eq!(architecture.family, "loongarch64");
+        let architecture = parser.parse_architecture("Mozilla/5.0 (X11; SunOS i86pc)");
+        assert_eq!(architecture.family, "x86");
+
+        let parser = UserAgentParser::try_from(RegexFile::default())
+            .expect("Parser creation failed");
+        assert_eq!(parser.parse_architecture("Linux x86_64").family, "Other");
+    }
+}
@@ -0,0 +1,102 @@
+use std::collections::HashMap;
+
+use super::*;
+
+impl UserAgentParser {
+    /// Parses every user agent string in `user_agents`, returning the
+    /// `Client`s in the same order. Identical strings within the batch are
+    /// only parsed once, which pays off for log files where a handful of
+    /// agents make up most of the lines.
+    ///
+    /// ```rust
+    /// # use uaparser::*;
+    /// let regexes = br"
+    /// user_agent_parsers:
+    ///   - regex: '(Firefox)/(\d+)\.(\d+)'
+    /// os_parsers: []
+    /// device_parsers: []
+    /// ";
+    /// let parser = UserAgentParser::from_bytes(regexes).unwrap();
+    /// let clients = parser.parse_batch(&["Firefox/115.0", "curl/8.0", "Firefox/115.0"]);
+    ///
+    /// assert_eq!(clients.len(), 3);
+    /// assert_eq!(clients[0], clients[2]);
+    /// assert_eq!(clients[1].user_agent.family, "Other");
+    /// ```
+    #[must_use]
+    pub fn parse_batch<'a>(&self, user_agents: &[&'a str]) -> Vec<Client<'a>> {
+        let (unique, slots) = dedupe(user_agents);
+        let clients: Vec<_> = unique.into_iter().map(|ua| self.parse(ua)).collect();
+        slots.into_iter().map(|i| clients[i].clone()).collect()
+    }
+
+    /// Like `parse_batch`, but spreads the unique strings of the batch across
+    /// the rayon thread pool. Requires the `rayon` feature.
+    #[cfg(feature = "rayon")]
+    #[must_use]
+    pub fn par_parse<'a>(&self, user_agents: &[&'a str]) -> Vec<Client<'a>> {
+        use rayon::prelude::*;
+
+        let (unique, slots) = dedupe(user_agents);
+        let clients: Vec<_> = unique.into_par_iter().map(|ua| self.parse(ua)).collect();
+        slots.into_par_iter().map(|i| clients[i].clone()).collect()
+    }
+}
+
+/// Splits `user_agents` into its distinct strings, in order of first
+/// appearance, and the index into those of each original string.
+fn dedupe<'a>(user_agents: &[&'a str]) -> (Vec<&'a str>, Vec<usize>) {
+    let mut seen = HashMap::with_capacity(user_agents.len());
+    let mut unique = Vec::new();
+    let slots = user_agents
+        .iter()
+        .map(|&ua| {
+            *seen.entry(ua).or_insert_with(|| {
+                unique.push(ua);
+                unique.len() - 1
+            })
+        })
+        .collect();
+    (unique, slots)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const REGEXES: &[u8] = br"
+user_agent_parsers:
+  - regex: '(Firefox)/(\d+)\.(\d+)'
+  - regex: '(Chrome)/(\d+)\.(\d+)'
+os_parsers:
+  - regex: '(Linux)'
+device_parsers: []
+";
+
+    const USER_AGENTS: &[&str] = &[
+        "Mozilla/5.0 (X11; Linux x86_64) Firefox/115.0",
+        "Mozilla/5.0 (Windows NT 10.0) Chrome/114.0",
+        "Mozilla/5.0 (X11; Linux x86_64) Firefox/115.0",
+        "curl/8.0",
+        "Mozilla/5.0 (Windows NT 10.0) Chrome/114.0",
+    ];
+
+    #[test]
+    fn dedupes_in_order_of_appearance() {
+        let (unique, slots) = dedupe(USER_AGENTS);
+        assert_eq!(unique, vec![USER_AGENTS[0], USER_AGENTS[1], USER_AGENTS[3]]);
+        assert_eq!(slots, vec![0, 1, 0, 2, 1]);
+    }
+
+    #[test]
+    fn batch_matches_individual_parses() {
+        let parser =
+            UserAgentParser::from_bytes(REGEXES).expect("Parser creation failed");
+        let expected: Vec<_> = USER_AGENTS.iter().map(|ua| parser.parse(ua)).collect();
+
+        assert_eq!(parser.parse_batch(USER_AGENTS), expected);
+        assert!(parser.parse_batch(&[]).is_empty());
+        #[cfg(feature = "rayon")]
+        assert_eq!(parser.par_parse(USER_AGENTS), expected);
+    }
+}
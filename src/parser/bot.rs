@@ -0,0 +1,268 @@
+use serde_derive::Deserialize;
+
+use super::*;
+
+#[derive(Debug, Display, From)]
+pub enum Error {
+    Regex(regex::Error),
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Regex(e) => e.source(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Matcher {
+    regex: LazyRegex,
+    name_replacement: Option<Template>,
+    category: BotCategory,
+    vendor: Option<Arc<str>>,
+}
+
+impl<'a> SubParser<'a> for Matcher {
+    type Item = BotInfo<'a>;
+
+    fn try_parse(&self, text: &'a str) -> Option<Self::Item> {
+        let captures = self.regex.get()?.captures(text.as_bytes())?;
+
+        let name: Cow<'a, str> = match &self.name_replacement {
+            Some(name_replacement) => name_replacement.expand(&captures),
+            None => template::capture(&captures, 1)?,
+        };
+
+        Some(BotInfo {
+            name,
+            category: self.category,
+            vendor: self.vendor.clone(),
+        })
+    }
+}
+
+impl Pattern for Matcher {
+    fn regex(&self) -> &LazyRegex {
+        &self.regex
+    }
+}
+
+impl Explain<'_> for Matcher {
+    fn replacements(&self, item: &BotInfo<'_>) -> Vec<Replacement> {
+        replacement("name", self.name_replacement.as_ref(), Some(&item.name))
+            .into_iter()
+            .collect()
+    }
+}
+
+impl Matcher {
+    pub fn try_from(
+        entry: BotParserEntry,
+        unicode: bool,
+        lazy: bool,
+    ) -> Result<Matcher, Error> {
+        let regex = LazyRegex::new(
+            clean_escapes(&entry.regex).into_owned(),
+            unicode,
+            Some(20 * (1 << 20)),
+            lazy,
+        )?;
+
+        Ok(Matcher {
+            regex,
+            name_replacement: entry.name_replacement.map(Template::new),
+            category: entry.category,
+            vendor: entry.vendor.map(Arc::from),
+        })
+    }
+
+    pub fn from_precompiled(
+        entry: &PrecompiledBotEntry,
+        unicode: bool,
+        lazy: bool,
+    ) -> Result<Matcher, Error> {
+        Ok(Matcher {
+            regex: LazyRegex::new(
+                entry.regex.to_owned(),
+                unicode,
+                Some(20 * (1 << 20)),
+                lazy,
+            )?,
+            name_replacement: entry.name_replacement.map(Template::from),
+            category: entry.category,
+            vendor: entry.vendor.map(Arc::from),
+        })
+    }
+}
+
+/// The rules from the `bots.yaml` bundled with this crate.
+pub(super) fn builtin_rules() -> Vec<BotParserEntry> {
+    #[derive(Deserialize)]
+    struct BotsFile {
+        bot_parsers: Vec<BotParserEntry>,
+    }
+
+    let file: BotsFile = serde_yaml::from_slice(BUILTIN_BOTS)
+        .expect("bundled bots.yaml should always be valid");
+    file.bot_parsers
+}
+
+static BUILTIN_BOTS: &[u8] = include_bytes!("../bots.yaml");
+
+/// The name rule errors and explanations give the bundled bot rules.
+pub(super) const BUILTIN_NAME: &str = "bots.yaml";
+
+/// Classifies agents that only the device rules recognized, which mark most
+/// crawlers as a `Spider` without naming them.
+pub(super) fn from_spider<'a>(
+    device: &Device<'_>,
+    user_agent: &UserAgent<'a>,
+) -> Option<BotInfo<'a>> {
+    if device.family != "Spider" {
+        return None;
+    }
+
+    let name = if user_agent.family == "Other" {
+        Cow::Borrowed("Spider")
+    } else {
+        user_agent.family.clone()
+    };
+
+    Some(BotInfo {
+        name,
+        category: BotCategory::Crawler,
+        vendor: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::{BotCategory, BotRule, Parser, Precedence, RegexFile, UserAgentParser};
+
+    const REGEXES: &str = r"
+user_agent_parsers:
+  - regex: '(Firefox)/(\d+)\.(\d+)'
+  - regex: '(HeadlessChrome)/(\d+)'
+os_parsers: []
+device_parsers:
+  - regex: '(?i)(?:crawler|spider)'
+    device_replacement: 'Spider'
+    brand_replacement: 'Spider'
+bot_parsers:
+  - regex: '(InternalCrawler)/'
+    category: crawler
+    vendor: 'Example'
+";
+
+    fn parser() -> UserAgentParser {
+        UserAgentParser::builder()
+            .with_bot(true)
+            .build_from_bytes(REGEXES.as_bytes())
+            .expect("Parser creation failed")
+    }
+
+    #[test]
+    fn builtin_rules_are_valid() {
+        let parser = UserAgentParser::builder()
+            .with_bot(true)
+            .with_lazy_compilation(true)
+            .build_from_regex_file(RegexFile::default())
+            .expect("Parser creation failed");
+        parser.validate().expect("Invalid bot rule");
+    }
+
+    #[test]
+    fn classifies_bots() {
+        let parser = parser();
+        for (ua, name, category, vendor) in [
+            (
+                "Mozilla/5.0 (compatible; Googlebot/2.1; +http://www.google.com/bot.html)",
+                "Googlebot",
+                BotCategory::SearchEngine,
+                Some("Google"),
+            ),
+            (
+                "Mozilla/5.0 (compatible; AhrefsBot/7.0; +http://ahrefs.com/robot/)",
+                "AhrefsBot",
+                BotCategory::SeoTool,
+                Some("Ahrefs"),
+            ),
+            (
+                "Pingdom.com_bot_version_1.4_(http://www.pingdom.com/)",
+                "Pingdom",
+                BotCategory::Monitoring,
+                Some("Pingdom"),
+            ),
+            (
+                "Mozilla/5.0 (X11; Linux x86_64) HeadlessChrome/114.0.5735.133 Safari/537.36",
+                "HeadlessChrome",
+                BotCategory::HeadlessBrowser,
+                Some("Google"),
+            ),
+            ("python-requests/2.31.0", "python-requests", BotCategory::HttpLibrary, None),
+            ("curl/8.0.1", "curl", BotCategory::HttpLibrary, None),
+            ("InternalCrawler/1.0", "InternalCrawler", BotCategory::Crawler, Some("Example")),
+        ] {
+            let bot = parser.parse_bot(ua).expect(ua);
+            assert_eq!(bot.name, name);
+            assert_eq!(bot.category, category);
+            assert_eq!(bot.vendor.as_deref(), vendor);
+            assert_eq!(parser.parse(ua).bot, Some(bot));
+        }
+
+        let client = parser.parse("Mozilla/5.0 (X11; Linux x86_64) Firefox/115.0");
+        assert!(!client.is_bot());
+
+        let vendor = |ua| parser.parse_bot(ua).and_then(|bot| bot.vendor).expect(ua);
+        assert!(Arc::ptr_eq(
+            &vendor("InternalCrawler/1.0"),
+            &vendor("InternalCrawler/2.0")
+        ));
+    }
+
+    #[test]
+    fn spider_devices_are_bots() {
+        let parser = parser();
+        let bot = parser
+            .parse_bot("Mozilla/5.0 (compatible; ExampleSpider/1.0) Firefox/115.0")
+            .expect("No bot");
+        assert_eq!(bot.name, "Firefox");
+        assert_eq!(bot.category, BotCategory::Crawler);
+
+        let bot = parser.parse_bot("some crawler").expect("No bot");
+        assert_eq!(bot.name, "Spider");
+    }
+
+    #[test]
+    fn builtin_rules_can_be_replaced_or_disabled() {
+        let overlay = RegexFile::default().bot(
+            BotRule::new("(curl)/", BotCategory::Monitoring)
+                .id("curl")
+                .name("Health check"),
+        );
+        let parser = UserAgentParser::builder()
+            .with_bot(true)
+            .with_overlay("health.yaml", overlay, Precedence::ReplaceById)
+            .build_from_bytes(REGEXES.as_bytes())
+            .expect("Parser creation failed");
+        let bot = parser.parse_bot("curl/8.0.1").expect("No bot");
+        assert_eq!(bot.name, "Health check");
+        assert_eq!(bot.category, BotCategory::Monitoring);
+
+        let parser = UserAgentParser::builder()
+            .with_bot(true)
+            .with_builtin_bots(false)
+            .build_from_bytes(REGEXES.as_bytes())
+            .expect("Parser creation failed");
+        assert!(parser.parse_bot("curl/8.0.1").is_none());
+        assert!(parser.parse_bot("InternalCrawler/1.0").is_some());
+
+        let parser = UserAgentParser::from_bytes(REGEXES.as_bytes())
+            .expect("Parser creation failed");
+        assert!(parser.parse_bot("InternalCrawler/1.0").is_none());
+        assert!(parser.parse_bot("some crawler").is_none());
+    }
+}
@@ -1,10 +1,29 @@
-use crate::{Error, UserAgentParser};
+use super::{
+    codegen::PrecompiledRegexes,
+    overlay::{Overlay, Precedence, Source},
+};
+use crate::{Error, RegexFile, UserAgentParser};
 
+/// Configures and builds a `UserAgentParser`, see `UserAgentParser::builder`.
+#[derive(Clone)]
+#[must_use]
+#[allow(clippy::struct_excessive_bools)]
 pub struct UserAgentParserBuilder {
     pub(super) device: bool,
     pub(super) os: bool,
     pub(super) user_agent: bool,
+    pub(super) engine: bool,
+    pub(super) builtin_engines: bool,
+    pub(super) architecture: bool,
+    pub(super) builtin_architectures: bool,
+    pub(super) bot: bool,
+    pub(super) builtin_bots: bool,
     pub(super) unicode: bool,
+    pub(super) regex_set: bool,
+    pub(super) literal_prefilter: bool,
+    pub(super) lazy: bool,
+    pub(super) lenient: bool,
+    pub(super) overlays: Vec<Overlay>,
 }
 
 impl UserAgentParserBuilder {
@@ -13,7 +32,18 @@ impl UserAgentParserBuilder {
             device: true,
             os: true,
             user_agent: true,
+            engine: false,
+            builtin_engines: true,
+            architecture: false,
+            builtin_architectures: true,
+            bot: false,
+            builtin_bots: true,
             unicode: true,
+            regex_set: true,
+            literal_prefilter: false,
+            lazy: false,
+            lenient: false,
+            overlays: Vec::new(),
         }
     }
 
@@ -50,9 +80,164 @@ impl UserAgentParserBuilder {
         self
     }
 
+    /// Enable or disable engine parsing. This is disabled by default.
+    /// With this disabled `Client::engine` is always `Other`, and the bundled
+    /// engine rules are not loaded.
+    pub fn with_engine(mut self, enabled: bool) -> Self {
+        self.engine = enabled;
+        self
+    }
+
+    /// Enable or disable the engine rules bundled with this crate. This is
+    /// enabled by default, but only has an effect along with
+    /// `with_engine(true)`. The bundled rules are tried after any
+    /// `engine_parsers` in the regexes file, and each has an `id` so an
+    /// overlay can replace it.
+    pub fn with_builtin_engines(mut self, enabled: bool) -> Self {
+        self.builtin_engines = enabled;
+        self
+    }
+
+    /// Enable or disable architecture parsing. This is disabled by default.
+    /// With this disabled `Client::architecture` is always `Other`, and the
+    /// bundled architecture rules are not loaded.
+    pub fn with_architecture(mut self, enabled: bool) -> Self {
+        self.architecture = enabled;
+        self
+    }
+
+    /// Enable or disable the architecture rules bundled with this crate. This
+    /// is enabled by default, but only has an effect along with
+    /// `with_architecture(true)`. The bundled rules are tried after any
+    /// `architecture_parsers` in the regexes file, and each has an `id` so an
+    /// overlay can replace it.
+    pub fn with_builtin_architectures(mut self, enabled: bool) -> Self {
+        self.builtin_architectures = enabled;
+        self
+    }
+
+    /// Enable or disable bot classification. This is disabled by default.
+    /// With this disabled `Client::bot` is never set, and the bundled bot
+    /// rules are not loaded.
+    pub fn with_bot(mut self, enabled: bool) -> Self {
+        self.bot = enabled;
+        self
+    }
+
+    /// Enable or disable the bot rules bundled with this crate. This is
+    /// enabled by default, but only has an effect along with
+    /// `with_bot(true)`. The bundled rules are tried after any
+    /// `bot_parsers` in the regexes file, and each has an `id` so an overlay
+    /// can replace it.
+    pub fn with_builtin_bots(mut self, enabled: bool) -> Self {
+        self.builtin_bots = enabled;
+        self
+    }
+
+    /// Enable or disable the `RegexSet` prefilter. This is enabled by default.
+    /// Each list of regexes is additionally compiled into a set of `RegexSet`s
+    /// so a single pass finds the matching rules, which makes strings that
+    /// fall through to "Other" much cheaper to parse. Disabling this roughly
+    /// halves the memory used by the compiled regexes.
+    pub fn with_regex_set_prefilter(mut self, enabled: bool) -> Self {
+        self.regex_set = enabled;
+        self
+    }
+
+    /// Enable or disable the literal prefilter. This is disabled by default.
+    /// Most rules require a literal such as `Firefox` or `CrOS` to be present
+    /// in the string. With this enabled those literals are extracted up front
+    /// and the string is scanned for all of them at once, so only rules whose
+    /// literals were seen are evaluated. This costs some extra build time.
+    pub fn with_literal_prefilter(mut self, enabled: bool) -> Self {
+        self.literal_prefilter = enabled;
+        self
+    }
+
+    /// Enable or disable lazy compilation. This is disabled by default.
+    /// Instead of compiling every regex up front, each one is compiled the
+    /// first time it is needed, which makes building the parser much cheaper
+    /// for short-lived processes. The prefilters are left out until
+    /// `UserAgentParser::warm_up` is called, as building them would compile
+    /// every pattern at once. Invalid patterns are no longer reported when
+    /// the parser is built; call `UserAgentParser::validate` to check them, or
+    /// `UserAgentParser::warm_up` to compile everything ahead of time.
+    pub fn with_lazy_compilation(mut self, enabled: bool) -> Self {
+        self.lazy = enabled;
+        self
+    }
+
+    /// Enable or disable lenient loading. This is disabled by default.
+    /// Normally a single rule whose regex fails to compile, such as one using
+    /// a lookaround, makes building the parser fail. With this enabled such
+    /// rules are left out instead and listed by
+    /// `UserAgentParser::skipped_rules`. Lazily compiled patterns are not
+    /// checked when the parser is built, and an invalid one simply never
+    /// matches.
+    pub fn with_lenient_loading(mut self, enabled: bool) -> Self {
+        self.lenient = enabled;
+        self
+    }
+
+    /// Merges the rules of `regex_file` into the file the parser is built
+    /// from, according to `precedence`. Overlays are applied in the order
+    /// they are added, so a later `Prepend` overlay wins over an earlier one.
+    /// Errors caused by an overlay's rules are reported as
+    /// `Error::Overlay`, carrying `name` to identify the file.
+    ///
+    /// Precompiled rules should be merged before running `codegen` instead,
+    /// so `build_from_precompiled` fails with an `Error::Overlay` wrapping
+    /// `Error::PrecompiledOverlay` if any overlays are set.
+    pub fn with_overlay(
+        mut self,
+        name: &str,
+        regex_file: RegexFile,
+        precedence: Precedence,
+    ) -> Self {
+        self.overlays.push(Overlay {
+            name: name.to_owned(),
+            source: Source::RegexFile(regex_file),
+            precedence,
+        });
+        self
+    }
+
+    /// Like `with_overlay`, but takes a slice of raw YAML bytes. The YAML is
+    /// only deserialized when the parser is built.
+    ///
+    /// ```rust
+    /// # use uaparser::*;
+    /// let overlay = br"
+    /// user_agent_parsers:
+    ///   - regex: '(InternalApp)/(\d+)'
+    /// ";
+    /// let parser = UserAgentParser::builder()
+    ///     .with_overlay_bytes("internal.yaml", overlay, Precedence::Prepend)
+    ///     .build_from_yaml("./src/core/regexes.yaml");
+    /// ```
+    pub fn with_overlay_bytes(
+        mut self,
+        name: &str,
+        bytes: &[u8],
+        precedence: Precedence,
+    ) -> Self {
+        self.overlays.push(Overlay {
+            name: name.to_owned(),
+            source: Source::Yaml(bytes.to_vec()),
+            precedence,
+        });
+        self
+    }
+
     pub fn build_from_yaml(self, path: &str) -> Result<UserAgentParser, Error> {
-        UserAgentParser::_build_from_yaml(path, self)
+        UserAgentParser::_build_from_yaml(path, &self)
     }
+
+    /// Attempts to construct a `UserAgentParser` from an open `regexes.yaml`.
+    pub fn build_from_file(self, file: std::fs::File) -> Result<UserAgentParser, Error> {
+        UserAgentParser::_build_from_file(file, &self)
+    }
+
     /// Attempts to construct a `UserAgentParser` from a slice of raw bytes. The
     /// intention with providing this function is to allow using the
     /// `include_bytes!` macro to compile the `regexes.yaml` file into the
@@ -64,6 +249,38 @@ impl UserAgentParserBuilder {
     /// let parser = UserAgentParser::builder().build_from_bytes(regexes);
     /// ```
     pub fn build_from_bytes(self, bytes: &[u8]) -> Result<UserAgentParser, Error> {
-        UserAgentParser::_build_from_bytes(bytes, self)
+        UserAgentParser::_build_from_bytes(bytes, &self)
+    }
+
+    /// Attempts to construct a `UserAgentParser` from a `RegexFile`, such as
+    /// one whose rules were defined in code.
+    pub fn build_from_regex_file(
+        self,
+        regex_file: RegexFile,
+    ) -> Result<UserAgentParser, Error> {
+        UserAgentParser::_try_from(regex_file, &self)
+    }
+
+    /// Attempts to construct a `UserAgentParser` from the output of
+    /// `codegen::generate`, see the `codegen` module for details.
+    pub fn build_from_precompiled(
+        self,
+        regexes: &PrecompiledRegexes,
+    ) -> Result<UserAgentParser, Error> {
+        UserAgentParser::_build_from_precompiled(regexes, &self)
+    }
+
+    /// Attempts to construct a `UserAgentParser` from the `regexes.yaml`
+    /// bundled with this crate by the `embedded-regexes` feature.
+    ///
+    /// ```rust
+    /// # use uaparser::*;
+    /// let parser = UserAgentParser::builder()
+    ///     .with_unicode_support(false)
+    ///     .build_embedded();
+    /// ```
+    #[cfg(feature = "embedded-regexes")]
+    pub fn build_embedded(self) -> Result<UserAgentParser, Error> {
+        UserAgentParser::_build_embedded(&self)
     }
 }
@@ -0,0 +1,491 @@
+//! Turns a `regexes.yaml` file into Rust source at build time.
+//!
+//! Loading a parser normally means deserializing the YAML and cleaning up
+//! every pattern before any regex is compiled. For short-lived processes that
+//! work can be moved into a build script instead: the generated source
+//! contains the final, validated patterns, so startup only has to compile the
+//! regexes and split up their replacements.
+//!
+//! In `build.rs`:
+//! ```rust,no_run
+//! let source = uaparser::codegen::generate_from_yaml("regexes.yaml", "REGEXES")
+//!     .expect("Code generation failed");
+//! let out_dir = std::env::var("OUT_DIR").unwrap();
+//! std::fs::write(format!("{}/regexes.rs", out_dir), source).unwrap();
+//! ```
+//!
+//! And in the crate itself:
+//! ```rust,ignore
+//! include!(concat!(env!("OUT_DIR"), "/regexes.rs"));
+//!
+//! let parser = UserAgentParser::from_precompiled(&REGEXES)?;
+//! ```
+
+use std::fmt::{self, Write};
+
+use super::*;
+
+/// A `regexes.yaml` file as produced by `generate`.
+#[derive(Debug, Clone, Copy)]
+pub struct PrecompiledRegexes {
+    pub user_agent_parsers: &'static [PrecompiledUserAgentEntry],
+    pub os_parsers: &'static [PrecompiledOSEntry],
+    pub device_parsers: &'static [PrecompiledDeviceEntry],
+    pub engine_parsers: &'static [PrecompiledEngineEntry],
+    pub architecture_parsers: &'static [PrecompiledArchitectureEntry],
+    pub bot_parsers: &'static [PrecompiledBotEntry],
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct PrecompiledUserAgentEntry {
+    pub regex: &'static str,
+    pub family_replacement: Option<&'static str>,
+    pub v1_replacement: Option<&'static str>,
+    pub v2_replacement: Option<&'static str>,
+    pub v3_replacement: Option<&'static str>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct PrecompiledOSEntry {
+    pub regex: &'static str,
+    pub os_replacement: Option<&'static str>,
+    pub os_v1_replacement: Option<&'static str>,
+    pub os_v2_replacement: Option<&'static str>,
+    pub os_v3_replacement: Option<&'static str>,
+    pub os_v4_replacement: Option<&'static str>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct PrecompiledDeviceEntry {
+    pub regex: &'static str,
+    pub device_replacement: Option<&'static str>,
+    pub brand_replacement: Option<&'static str>,
+    pub model_replacement: Option<&'static str>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct PrecompiledEngineEntry {
+    pub regex: &'static str,
+    pub family_replacement: Option<&'static str>,
+    pub v1_replacement: Option<&'static str>,
+    pub v2_replacement: Option<&'static str>,
+    pub v3_replacement: Option<&'static str>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct PrecompiledArchitectureEntry {
+    pub regex: &'static str,
+    pub family_replacement: Option<&'static str>,
+    pub emulated: bool,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct PrecompiledBotEntry {
+    pub regex: &'static str,
+    pub name_replacement: Option<&'static str>,
+    pub category: BotCategory,
+    pub vendor: Option<&'static str>,
+}
+
+impl Rule for PrecompiledUserAgentEntry {
+    fn rule_id(&self) -> Option<&str> {
+        None
+    }
+
+    fn pattern(&self) -> &str {
+        self.regex
+    }
+}
+
+impl Rule for PrecompiledOSEntry {
+    fn rule_id(&self) -> Option<&str> {
+        None
+    }
+
+    fn pattern(&self) -> &str {
+        self.regex
+    }
+}
+
+impl Rule for PrecompiledDeviceEntry {
+    fn rule_id(&self) -> Option<&str> {
+        None
+    }
+
+    fn pattern(&self) -> &str {
+        self.regex
+    }
+}
+
+impl Rule for PrecompiledEngineEntry {
+    fn rule_id(&self) -> Option<&str> {
+        None
+    }
+
+    fn pattern(&self) -> &str {
+        self.regex
+    }
+}
+
+impl Rule for PrecompiledArchitectureEntry {
+    fn rule_id(&self) -> Option<&str> {
+        None
+    }
+
+    fn pattern(&self) -> &str {
+        self.regex
+    }
+}
+
+impl Rule for PrecompiledBotEntry {
+    fn rule_id(&self) -> Option<&str> {
+        None
+    }
+
+    fn pattern(&self) -> &str {
+        self.regex
+    }
+}
+
+/// Generates the source for a `PrecompiledRegexes` static named `name` from
+/// the file at `path`.
+pub fn generate_from_yaml(path: &str, name: &str) -> Result<String, Error> {
+    let bytes = std::fs::read(path)?;
+    generate_from_bytes(&bytes, name)
+}
+
+/// Generates the source for a `PrecompiledRegexes` static named `name` from a
+/// slice of raw YAML bytes.
+pub fn generate_from_bytes(bytes: &[u8], name: &str) -> Result<String, Error> {
+    let regex_file: RegexFile = serde_yaml::from_slice(bytes)?;
+    generate(&regex_file, name).map_err(|mut e| {
+        overlay::find_location(&mut e, Some(bytes), &[]);
+        e
+    })
+}
+
+/// Generates the source for a `PrecompiledRegexes` static named `name`. Every
+/// pattern is compiled once with the default settings, so a file that would
+/// fail to load is rejected here rather than at runtime.
+pub fn generate(regex_file: &RegexFile, name: &str) -> Result<String, Error> {
+    let mut out = String::new();
+    write_source(regex_file, name, &mut out)?;
+    Ok(out)
+}
+
+const PATH: &str = "::uaparser::codegen";
+
+fn write_source(
+    regex_file: &RegexFile,
+    name: &str,
+    out: &mut String,
+) -> Result<(), Error> {
+    let _ = writeln!(out, "// @generated by uaparser::codegen, do not edit.");
+    let _ = writeln!(
+        out,
+        "pub static {name}: {PATH}::PrecompiledRegexes = {PATH}::PrecompiledRegexes {{"
+    );
+    write_user_agent_parsers(regex_file, out)?;
+    write_os_parsers(regex_file, out)?;
+    write_device_parsers(regex_file, out)?;
+    write_engine_parsers(regex_file, out)?;
+    write_architecture_parsers(regex_file, out)?;
+    write_bot_parsers(regex_file, out)?;
+    let _ = writeln!(out, "}};");
+    Ok(())
+}
+
+fn write_user_agent_parsers(
+    regex_file: &RegexFile,
+    out: &mut String,
+) -> Result<(), Error> {
+    let _ = writeln!(out, "    user_agent_parsers: &[");
+    for (i, entry) in regex_file.user_agent_parsers.iter().enumerate() {
+        let regex = clean_escapes(&entry.regex).into_owned();
+        validate(&regex, true).map_err(|e| {
+            locate(
+                &[],
+                Section::UserAgent,
+                i,
+                &entry.regex,
+                UserAgentError::Regex(e),
+            )
+        })?;
+
+        let _ = writeln!(out, "        {PATH}::PrecompiledUserAgentEntry {{");
+        field(out, "regex", &regex);
+        field(
+            out,
+            "family_replacement",
+            entry.family_replacement.as_deref(),
+        );
+        field(out, "v1_replacement", entry.v1_replacement.as_deref());
+        field(out, "v2_replacement", entry.v2_replacement.as_deref());
+        field(out, "v3_replacement", entry.v3_replacement.as_deref());
+        let _ = writeln!(out, "        }},");
+    }
+    let _ = writeln!(out, "    ],");
+    Ok(())
+}
+
+fn write_os_parsers(regex_file: &RegexFile, out: &mut String) -> Result<(), Error> {
+    let _ = writeln!(out, "    os_parsers: &[");
+    for (i, entry) in regex_file.os_parsers.iter().enumerate() {
+        let regex = clean_escapes(&entry.regex).into_owned();
+        validate(&regex, false)
+            .map_err(|e| locate(&[], Section::OS, i, &entry.regex, OSError::Regex(e)))?;
+
+        let _ = writeln!(out, "        {PATH}::PrecompiledOSEntry {{");
+        field(out, "regex", &regex);
+        field(out, "os_replacement", entry.os_replacement.as_deref());
+        field(out, "os_v1_replacement", entry.os_v1_replacement.as_deref());
+        field(out, "os_v2_replacement", entry.os_v2_replacement.as_deref());
+        field(out, "os_v3_replacement", entry.os_v3_replacement.as_deref());
+        field(out, "os_v4_replacement", entry.os_v4_replacement.as_deref());
+        let _ = writeln!(out, "        }},");
+    }
+    let _ = writeln!(out, "    ],");
+    Ok(())
+}
+
+fn write_device_parsers(regex_file: &RegexFile, out: &mut String) -> Result<(), Error> {
+    let _ = writeln!(out, "    device_parsers: &[");
+    for (i, entry) in regex_file.device_parsers.iter().enumerate() {
+        let regex = device::regex_source(entry);
+        validate(&regex, true).map_err(|e| {
+            locate(&[], Section::Device, i, &entry.regex, DeviceError::Regex(e))
+        })?;
+
+        let _ = writeln!(out, "        {PATH}::PrecompiledDeviceEntry {{");
+        field(out, "regex", &regex);
+        field(
+            out,
+            "device_replacement",
+            entry.device_replacement.as_deref(),
+        );
+        field(out, "brand_replacement", entry.brand_replacement.as_deref());
+        field(out, "model_replacement", entry.model_replacement.as_deref());
+        let _ = writeln!(out, "        }},");
+    }
+    let _ = writeln!(out, "    ],");
+    Ok(())
+}
+
+fn write_engine_parsers(regex_file: &RegexFile, out: &mut String) -> Result<(), Error> {
+    let _ = writeln!(out, "    engine_parsers: &[");
+    for (i, entry) in regex_file.engine_parsers.iter().enumerate() {
+        let regex = clean_escapes(&entry.regex).into_owned();
+        validate(&regex, false).map_err(|e| {
+            locate(&[], Section::Engine, i, &entry.regex, EngineError::Regex(e))
+        })?;
+
+        let _ = writeln!(out, "        {PATH}::PrecompiledEngineEntry {{");
+        field(out, "regex", &regex);
+        field(
+            out,
+            "family_replacement",
+            entry.family_replacement.as_deref(),
+        );
+        field(out, "v1_replacement", entry.v1_replacement.as_deref());
+        field(out, "v2_replacement", entry.v2_replacement.as_deref());
+        field(out, "v3_replacement", entry.v3_replacement.as_deref());
+        let _ = writeln!(out, "        }},");
+    }
+    let _ = writeln!(out, "    ],");
+    Ok(())
+}
+
+fn write_architecture_parsers(
+    regex_file: &RegexFile,
+    out: &mut String,
+) -> Result<(), Error> {
+    let _ = writeln!(out, "    architecture_parsers: &[");
+    for (i, entry) in regex_file.architecture_parsers.iter().enumerate() {
+        let regex = clean_escapes(&entry.regex).into_owned();
+        validate(&regex, false).map_err(|e| {
+            locate(
+                &[],
+                Section::Architecture,
+                i,
+                &entry.regex,
+                ArchitectureError::Regex(e),
+            )
+        })?;
+
+        let _ = writeln!(out, "        {PATH}::PrecompiledArchitectureEntry {{");
+        field(out, "regex", &regex);
+        field(
+            out,
+            "family_replacement",
+            entry.family_replacement.as_deref(),
+        );
+        field(out, "emulated", entry.emulated);
+        let _ = writeln!(out, "        }},");
+    }
+    let _ = writeln!(out, "    ],");
+    Ok(())
+}
+
+fn write_bot_parsers(regex_file: &RegexFile, out: &mut String) -> Result<(), Error> {
+    let _ = writeln!(out, "    bot_parsers: &[");
+    for (i, entry) in regex_file.bot_parsers.iter().enumerate() {
+        let regex = clean_escapes(&entry.regex).into_owned();
+        validate(&regex, true).map_err(|e| {
+            locate(&[], Section::Bot, i, &entry.regex, BotError::Regex(e))
+        })?;
+
+        let _ = writeln!(out, "        {PATH}::PrecompiledBotEntry {{");
+        field(out, "regex", &regex);
+        field(out, "name_replacement", entry.name_replacement.as_deref());
+        let _ = writeln!(
+            out,
+            "            category: ::uaparser::BotCategory::{:?},",
+            entry.category
+        );
+        field(out, "vendor", entry.vendor.as_deref());
+        let _ = writeln!(out, "        }},");
+    }
+    let _ = writeln!(out, "    ],");
+    Ok(())
+}
+
+fn validate(pattern: &str, size_limit: bool) -> Result<(), regex::Error> {
+    let limit = if size_limit {
+        Some(20 * (1 << 20))
+    } else {
+        None
+    };
+    LazyRegex::new(pattern.to_owned(), true, limit, false).map(|_| ())
+}
+
+fn field(out: &mut String, name: &str, value: impl fmt::Debug) {
+    let _ = writeln!(out, "            {name}: {value:?},");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const REGEXES: &str = r"
+user_agent_parsers:
+  - regex: '(Firefox)/(\d+)\.(\d+)'
+  - regex: '(MSIE) (\d+)'
+    family_replacement: 'IE'
+os_parsers:
+  - regex: '(Windows NT) (\d+)\.(\d+)'
+    os_replacement: 'Windows'
+    os_v1_replacement: '$2'
+device_parsers:
+  - regex: '; *(SM-\w+)\ '
+    regex_flag: 'i'
+    device_replacement: 'Samsung $1'
+    brand_replacement: 'Samsung'
+bot_parsers:
+  - regex: '(InternalCrawler)/(\d+)'
+    category: crawler
+    vendor: 'Example'
+";
+
+    static PRECOMPILED: PrecompiledRegexes = PrecompiledRegexes {
+        user_agent_parsers: &[
+            PrecompiledUserAgentEntry {
+                regex: r"(Firefox)/(\d+)\.(\d+)",
+                family_replacement: None,
+                v1_replacement: None,
+                v2_replacement: None,
+                v3_replacement: None,
+            },
+            PrecompiledUserAgentEntry {
+                regex: r"(MSIE) (\d+)",
+                family_replacement: Some("IE"),
+                v1_replacement: None,
+                v2_replacement: None,
+                v3_replacement: None,
+            },
+        ],
+        os_parsers: &[PrecompiledOSEntry {
+            regex: r"(Windows NT) (\d+)\.(\d+)",
+            os_replacement: Some("Windows"),
+            os_v1_replacement: Some("$2"),
+            os_v2_replacement: None,
+            os_v3_replacement: None,
+            os_v4_replacement: None,
+        }],
+        device_parsers: &[PrecompiledDeviceEntry {
+            regex: r"(?i); *(SM-\w+) ",
+            device_replacement: Some("Samsung $1"),
+            brand_replacement: Some("Samsung"),
+            model_replacement: None,
+        }],
+        engine_parsers: &[],
+        architecture_parsers: &[],
+        bot_parsers: &[PrecompiledBotEntry {
+            regex: r"(InternalCrawler)/(\d+)",
+            name_replacement: None,
+            category: BotCategory::Crawler,
+            vendor: Some("Example"),
+        }],
+    };
+
+    #[test]
+    fn generated_source_matches_precompiled_entries() {
+        let source = generate_from_bytes(REGEXES.as_bytes(), "REGEXES")
+            .expect("Code generation failed");
+
+        assert!(source.starts_with("// @generated"));
+        assert!(source
+            .contains("pub static REGEXES: ::uaparser::codegen::PrecompiledRegexes = "));
+        assert!(source.contains(r#"regex: "(?i); *(SM-\\w+) ","#));
+        assert!(source.contains(r#"device_replacement: Some("Samsung $1"),"#));
+        assert!(source.contains(r#"os_v1_replacement: Some("$2"),"#));
+        assert_eq!(source.matches("PrecompiledUserAgentEntry {").count(), 2);
+        assert!(source.contains("category: ::uaparser::BotCategory::Crawler,"));
+    }
+
+    #[test]
+    fn precompiled_parser_agrees_with_yaml() {
+        let from_yaml = UserAgentParser::from_bytes(REGEXES.as_bytes())
+            .expect("Parser creation failed");
+        let precompiled = UserAgentParser::from_precompiled(&PRECOMPILED)
+            .expect("Parser creation failed");
+
+        for ua in [
+            "Mozilla/5.0 (Windows NT 10.0; Win64; x64; rv:109.0) Firefox/115.0",
+            "Mozilla/4.0 (compatible; MSIE 8; Windows NT 6.1)",
+            "Mozilla/5.0 (Linux; Android 13; sm-s918b ) AppleWebKit/537.36",
+            "InternalCrawler/2",
+            "curl/8.0.1",
+        ] {
+            assert_eq!(from_yaml.parse(ua), precompiled.parse(ua));
+        }
+    }
+
+    #[test]
+    fn overlays_are_rejected() {
+        let result = UserAgentParser::builder()
+            .with_overlay_bytes("internal", b"", Precedence::Prepend)
+            .build_from_precompiled(&PRECOMPILED);
+        match result {
+            Err(Error::Overlay(e)) => {
+                assert_eq!(e.name, "internal");
+                assert!(matches!(*e.error, Error::PrecompiledOverlay));
+            }
+            other => panic!("Unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn invalid_patterns_are_rejected() {
+        let yaml = "user_agent_parsers:\n  - regex: '(?<=a)b'\nos_parsers: []\ndevice_parsers: []\n";
+        match generate_from_bytes(yaml.as_bytes(), "REGEXES") {
+            Err(Error::Rule(e)) => {
+                assert_eq!(e.section, Section::UserAgent);
+                assert_eq!(e.index, 0);
+                assert_eq!(e.pattern, "(?<=a)b");
+                assert_eq!(e.location.map(|l| l.line), Some(2));
+                assert!(matches!(*e.error, Error::UserAgent(_)));
+            }
+            other => panic!("unexpected result: {:?}", other.map(|_| ())),
+        }
+    }
+}
@@ -5,103 +5,126 @@ pub enum Error {
     Regex(regex::Error),
 }
 
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Regex(e) => e.source(),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Matcher {
-    regex: regex::bytes::Regex,
-    device_replacement: Option<String>,
-    brand_replacement: Option<String>,
-    model_replacement: Option<String>,
-    device_replacement_has_group: bool,
-    brand_replacement_has_group: bool,
-    model_replacement_has_group: bool,
+    regex: LazyRegex,
+    device_replacement: Option<Template>,
+    brand_replacement: Option<Template>,
+    model_replacement: Option<Template>,
 }
 
 impl<'a> SubParser<'a> for Matcher {
     type Item = Device<'a>;
 
     fn try_parse(&self, text: &'a str) -> Option<Self::Item> {
-        if !self.regex.is_match(text.as_bytes()) {
+        let regex = self.regex.get()?;
+        if !regex.is_match(text.as_bytes()) {
             return None;
         }
 
-        if let Some(captures) = self.regex.captures(text.as_bytes()) {
-            let family: Cow<'a, str> =
-                if let Some(device_replacement) = &self.device_replacement {
-                    replace_cow(
-                        device_replacement,
-                        self.device_replacement_has_group,
-                        &captures,
-                    )
-                } else {
-                    captures
-                        .get(1)
-                        .and_then(match_to_str)
-                        .and_then(none_if_empty)
-                        .map(Cow::Borrowed)?
-                };
+        let captures = regex.captures(text.as_bytes())?;
+
+        let family: Cow<'a, str> = match &self.device_replacement {
+            Some(device_replacement) => device_replacement.expand(&captures),
+            None => template::capture(&captures, 1)?,
+        };
 
-            let brand: Option<Cow<'a, str>> = self
-                .brand_replacement
+        let brand: Option<Cow<'a, str>> =
+            self.brand_replacement
                 .as_ref()
-                .map(|br| replace_cow(br, self.brand_replacement_has_group, &captures))
-                .and_then(none_if_empty);
+                .and_then(|brand_replacement| {
+                    none_if_empty(brand_replacement.expand(&captures))
+                });
 
-            let model: Option<Cow<'a, str>> =
-                if let Some(model_replacement) = &self.model_replacement {
-                    none_if_empty(replace_cow(
-                        model_replacement,
-                        self.model_replacement_has_group,
-                        &captures,
-                    ))
-                } else {
-                    captures
-                        .get(1)
-                        .and_then(match_to_str)
-                        .and_then(none_if_empty)
-                        .map(Cow::Borrowed)
-                };
+        Some(Device {
+            family,
+            brand,
+            model: template::field(self.model_replacement.as_ref(), &captures, 1),
+        })
+    }
+}
 
-            Some(Device {
-                family,
-                brand,
-                model,
-            })
-        } else {
-            None
-        }
+impl Pattern for Matcher {
+    fn regex(&self) -> &LazyRegex {
+        &self.regex
+    }
+}
+
+impl Explain<'_> for Matcher {
+    fn replacements(&self, item: &Device<'_>) -> Vec<Replacement> {
+        vec![
+            replacement(
+                "family",
+                self.device_replacement.as_ref(),
+                Some(&item.family),
+            ),
+            replacement(
+                "brand",
+                self.brand_replacement.as_ref(),
+                item.brand.as_deref(),
+            ),
+            replacement(
+                "model",
+                self.model_replacement.as_ref(),
+                item.model.as_deref(),
+            ),
+        ]
+        .into_iter()
+        .flatten()
+        .collect()
     }
 }
 
 impl Matcher {
-    pub fn try_from(entry: DeviceParserEntry, unicode: bool) -> Result<Matcher, Error> {
-        let regex_with_flags = if entry.regex_flag.as_ref().map_or(true, String::is_empty)
-        {
-            entry.regex
-        } else {
-            format!("(?{}){}", entry.regex_flag.unwrap_or_default(), entry.regex)
-        };
-        let regex = regex::bytes::RegexBuilder::new(&clean_escapes(&regex_with_flags))
-            .size_limit(20 * (1 << 20))
-            .unicode(unicode)
-            .build();
+    pub fn try_from(
+        entry: DeviceParserEntry,
+        unicode: bool,
+        lazy: bool,
+    ) -> Result<Matcher, Error> {
+        let regex =
+            LazyRegex::new(regex_source(&entry), unicode, Some(20 * (1 << 20)), lazy)?;
 
         Ok(Matcher {
-            regex: regex?,
-            device_replacement_has_group: entry
-                .device_replacement
-                .as_ref()
-                .map_or(false, |x| has_group(x.as_str())),
-            device_replacement: entry.device_replacement,
-            brand_replacement_has_group: entry
-                .brand_replacement
-                .as_ref()
-                .map_or(false, |x| has_group(x.as_str())),
-            brand_replacement: entry.brand_replacement,
-            model_replacement_has_group: entry
-                .model_replacement
-                .as_ref()
-                .map_or(false, |x| has_group(x.as_str())),
-            model_replacement: entry.model_replacement,
+            regex,
+            device_replacement: entry.device_replacement.map(Template::new),
+            brand_replacement: entry.brand_replacement.map(Template::new),
+            model_replacement: entry.model_replacement.map(Template::new),
         })
     }
+
+    pub fn from_precompiled(
+        entry: &PrecompiledDeviceEntry,
+        unicode: bool,
+        lazy: bool,
+    ) -> Result<Matcher, Error> {
+        Ok(Matcher {
+            regex: LazyRegex::new(
+                entry.regex.to_owned(),
+                unicode,
+                Some(20 * (1 << 20)),
+                lazy,
+            )?,
+            device_replacement: entry.device_replacement.map(Template::from),
+            brand_replacement: entry.brand_replacement.map(Template::from),
+            model_replacement: entry.model_replacement.map(Template::from),
+        })
+    }
+}
+
+/// Returns the pattern for `entry` with its `regex_flag` applied and any
+/// escapes the regex crate rejects cleaned up.
+pub(super) fn regex_source(entry: &DeviceParserEntry) -> String {
+    let regex_with_flags = match entry.regex_flag.as_deref() {
+        None | Some("") => Cow::Borrowed(entry.regex.as_str()),
+        Some(flags) => Cow::Owned(format!("(?{}){}", flags, entry.regex)),
+    };
+    clean_escapes(&regex_with_flags).into_owned()
 }
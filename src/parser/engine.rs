@@ -0,0 +1,209 @@
+use serde_derive::Deserialize;
+
+use super::*;
+
+#[derive(Debug, Display, From)]
+pub enum Error {
+    Regex(regex::Error),
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Regex(e) => e.source(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Matcher {
+    regex: LazyRegex,
+    family_replacement: Option<Template>,
+    v1_replacement: Option<Template>,
+    v2_replacement: Option<Template>,
+    v3_replacement: Option<Template>,
+}
+
+impl<'a> SubParser<'a> for Matcher {
+    type Item = Engine<'a>;
+
+    fn try_parse(&self, text: &'a str) -> Option<Self::Item> {
+        let captures = self.regex.get()?.captures(text.as_bytes())?;
+
+        let family: Cow<'a, str> = match &self.family_replacement {
+            Some(family_replacement) => family_replacement.expand(&captures),
+            None => template::capture(&captures, 1)?,
+        };
+
+        Some(Engine {
+            family,
+            major: template::field(self.v1_replacement.as_ref(), &captures, 2),
+            minor: template::field(self.v2_replacement.as_ref(), &captures, 3),
+            patch: template::field(self.v3_replacement.as_ref(), &captures, 4),
+        })
+    }
+}
+
+impl Pattern for Matcher {
+    fn regex(&self) -> &LazyRegex {
+        &self.regex
+    }
+}
+
+impl Explain<'_> for Matcher {
+    fn replacements(&self, item: &Engine<'_>) -> Vec<Replacement> {
+        vec![
+            replacement(
+                "family",
+                self.family_replacement.as_ref(),
+                Some(&item.family),
+            ),
+            replacement("major", self.v1_replacement.as_ref(), item.major.as_deref()),
+            replacement("minor", self.v2_replacement.as_ref(), item.minor.as_deref()),
+            replacement("patch", self.v3_replacement.as_ref(), item.patch.as_deref()),
+        ]
+        .into_iter()
+        .flatten()
+        .collect()
+    }
+}
+
+impl Matcher {
+    pub fn try_from(
+        entry: EngineParserEntry,
+        unicode: bool,
+        lazy: bool,
+    ) -> Result<Matcher, Error> {
+        let regex = LazyRegex::new(
+            clean_escapes(&entry.regex).into_owned(),
+            unicode,
+            None,
+            lazy,
+        )?;
+
+        Ok(Matcher {
+            regex,
+            family_replacement: entry.family_replacement.map(Template::new),
+            v1_replacement: entry.v1_replacement.map(Template::new),
+            v2_replacement: entry.v2_replacement.map(Template::new),
+            v3_replacement: entry.v3_replacement.map(Template::new),
+        })
+    }
+
+    pub fn from_precompiled(
+        entry: &PrecompiledEngineEntry,
+        unicode: bool,
+        lazy: bool,
+    ) -> Result<Matcher, Error> {
+        Ok(Matcher {
+            regex: LazyRegex::new(entry.regex.to_owned(), unicode, None, lazy)?,
+            family_replacement: entry.family_replacement.map(Template::from),
+            v1_replacement: entry.v1_replacement.map(Template::from),
+            v2_replacement: entry.v2_replacement.map(Template::from),
+            v3_replacement: entry.v3_replacement.map(Template::from),
+        })
+    }
+}
+
+/// The rules from the `engines.yaml` bundled with this crate.
+pub(super) fn builtin_rules() -> Vec<EngineParserEntry> {
+    #[derive(Deserialize)]
+    struct EnginesFile {
+        engine_parsers: Vec<EngineParserEntry>,
+    }
+
+    let file: EnginesFile = serde_yaml::from_slice(BUILTIN_ENGINES)
+        .expect("bundled engines.yaml should always be valid");
+    file.engine_parsers
+}
+
+static BUILTIN_ENGINES: &[u8] = include_bytes!("../engines.yaml");
+
+/// What errors and `explain` call the bundled engine rules.
+pub(super) const BUILTIN_NAME: &str = "engines.yaml";
+
+#[cfg(test)]
+mod tests {
+    use crate::{EngineRule, Parser, RegexFile, UserAgentParser};
+
+    fn parser() -> UserAgentParser {
+        UserAgentParser::builder()
+            .with_engine(true)
+            .build_from_regex_file(RegexFile::default())
+            .expect("Parser creation failed")
+    }
+
+    #[test]
+    fn detects_engines() {
+        let parser = parser();
+        for (ua, family, version) in [
+            (
+                "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/114.0.5735.199 Safari/537.36",
+                "Blink",
+                "114.0.5735",
+            ),
+            (
+                "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/16.5 Safari/605.1.15",
+                "WebKit",
+                "605.1.15",
+            ),
+            (
+                "Mozilla/5.0 (iPhone; CPU iPhone OS 16_5 like Mac OS X) AppleWebKit/605.1.15 (KHTML, like Gecko) CriOS/114.0.5735.124 Mobile/15E148 Safari/604.1",
+                "WebKit",
+                "605.1.15",
+            ),
+            (
+                "Mozilla/5.0 (Windows NT 6.1; WOW64) AppleWebKit/535.7 (KHTML, like Gecko) Chrome/16.0.912.75 Safari/535.7",
+                "WebKit",
+                "535.7",
+            ),
+            (
+                "Mozilla/5.0 (X11; Linux x86_64; rv:109.0) Gecko/20100101 Firefox/115.0",
+                "Gecko",
+                "109.0",
+            ),
+            (
+                "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/70.0.3538.102 Safari/537.36 Edge/18.19582",
+                "EdgeHTML",
+                "18.19582",
+            ),
+            (
+                "Mozilla/5.0 (Windows NT 6.1; WOW64; Trident/7.0; rv:11.0) like Gecko",
+                "Trident",
+                "7.0",
+            ),
+            ("Opera/9.80 (Windows NT 6.1; U; en) Presto/2.12.388 Version/12.16", "Presto", "2.12.388"),
+        ] {
+            let engine = parser.parse_engine(ua);
+            assert_eq!(engine.family, family, "{ua}");
+            assert_eq!(engine.version().unwrap().to_string(), version, "{ua}");
+            assert_eq!(parser.parse(ua).engine, engine);
+        }
+
+        assert_eq!(parser.parse_engine("curl/8.0.1").family, "Other");
+    }
+
+    #[test]
+    fn rules_from_the_regexes_file_come_first() {
+        let regex_file =
+            RegexFile::default().engine(EngineRule::new(r"(Servo)/(\d+)\.(\d+)"));
+        let parser = UserAgentParser::builder()
+            .with_engine(true)
+            .build_from_regex_file(regex_file.clone())
+            .expect("Parser creation failed");
+        let engine = parser
+            .parse_engine("Mozilla/5.0 (X11; Linux x86_64) Servo/1.0 Firefox/111.0");
+        assert_eq!(engine.family, "Servo");
+
+        let parser = UserAgentParser::builder()
+            .with_engine(true)
+            .with_builtin_engines(false)
+            .build_from_regex_file(regex_file.clone())
+            .expect("Parser creation failed");
+        assert_eq!(parser.parse_engine("Gecko/20100101").family, "Other");
+
+        let parser =
+            UserAgentParser::try_from(regex_file).expect("Parser creation failed");
+        assert_eq!(parser.parse_engine("Servo/1.0").family, "Other");
+    }
+}
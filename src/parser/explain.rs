@@ -0,0 +1,127 @@
+use super::*;
+use crate::explain::{Replacement, RuleMatch};
+
+/// Lets a `Matcher` describe the replacements it applied to a result.
+pub(crate) trait Explain<'a>: SubParser<'a> + Pattern {
+    fn replacements(&self, item: &Self::Item) -> Vec<Replacement>;
+}
+
+/// Finds the rule in `matchers` that parses `text`, and describes the match.
+/// `origins` tells where each of the rules was defined.
+pub(super) fn explain<'a, M: Explain<'a>>(
+    matchers: &Matchers<M>,
+    origins: &[Origin],
+    text: &'a str,
+) -> Option<(M::Item, RuleMatch<'a>)> {
+    let (index, item) = matchers.find(text)?;
+    let matcher = matchers.get(index)?;
+    let regex = matcher.regex();
+    let captures = regex.get()?.captures(text.as_bytes())?;
+    let origin = Origin::of(origins, index);
+
+    let rule = RuleMatch {
+        file: origin.overlay,
+        index: origin.index,
+        regex: regex.as_str().to_owned(),
+        captures: captures.iter().map(|m| m.and_then(match_to_str)).collect(),
+        replacements: matcher.replacements(&item),
+    };
+
+    Some((item, rule))
+}
+
+/// Describes a single replacement, if the rule defines one for `field`.
+pub(super) fn replacement(
+    field: &'static str,
+    template: Option<&Template>,
+    value: Option<&str>,
+) -> Option<Replacement> {
+    Some(Replacement {
+        field,
+        template: template?.as_str().to_owned(),
+        value: value.map(str::to_owned),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Parser, Precedence, UserAgentParser};
+
+    const REGEXES: &str = r"
+user_agent_parsers:
+  - regex: '(Chrome)/(\d+)'
+  - regex: '(Firefox)/(\d+)\.(\d+)'
+    v2_replacement: 'x'
+os_parsers:
+  - regex: '(Windows NT) (\d+)\.(\d+)'
+    os_replacement: 'Windows'
+    os_v1_replacement: '$2'
+device_parsers:
+  - regex: '; *(SM-\w+) '
+    device_replacement: 'Samsung $1'
+";
+
+    #[test]
+    fn explanation_agrees_with_parse() {
+        let parser = UserAgentParser::from_bytes(REGEXES.as_bytes())
+            .expect("Parser creation failed");
+        let ua = "Mozilla/5.0 (Windows NT 10.0; SM-T510 ) Firefox/115.0";
+        let explanation = parser.explain(ua);
+
+        assert_eq!(explanation.client, parser.parse(ua));
+
+        let user_agent = explanation.user_agent.expect("No user agent rule");
+        assert_eq!((user_agent.file, user_agent.index), (None, 1));
+        assert_eq!(
+            user_agent.captures,
+            vec![
+                Some("Firefox/115.0"),
+                Some("Firefox"),
+                Some("115"),
+                Some("0")
+            ]
+        );
+        assert_eq!(user_agent.replacements.len(), 1);
+        assert_eq!(user_agent.replacements[0].field, "minor");
+
+        let os = explanation.os.expect("No OS rule");
+        assert_eq!(os.replacements.len(), 2);
+        assert_eq!(os.replacements[1].template, "$2");
+        assert_eq!(os.replacements[1].value.as_deref(), Some("10"));
+
+        let device = explanation.device.expect("No device rule");
+        assert_eq!(
+            device.replacements[0].value.as_deref(),
+            Some("Samsung SM-T510")
+        );
+
+        let explanation = parser.explain("curl/8.0.1");
+        assert!(explanation.user_agent.is_none());
+        assert_eq!(explanation.client.user_agent.family, "Other");
+    }
+
+    #[test]
+    fn names_the_file_the_rule_came_from() {
+        let overlay =
+            "user_agent_parsers:\n  - regex: '(?=Broken)'\n  - regex: '(Edg)/(\\d+)'\n";
+        let parser = UserAgentParser::builder()
+            .with_bot(true)
+            .with_lenient_loading(true)
+            .with_overlay_bytes("edge.yaml", overlay.as_bytes(), Precedence::Prepend)
+            .build_from_bytes(REGEXES.as_bytes())
+            .expect("Parser creation failed");
+
+        let explanation = parser.explain("Mozilla/5.0 Chrome/118 Edg/118");
+        let user_agent = explanation.user_agent.expect("No user agent rule");
+        assert_eq!(user_agent.file.as_deref(), Some("edge.yaml"));
+        assert_eq!(user_agent.index, 1);
+
+        let explanation = parser.explain("Mozilla/5.0 Firefox/115.0");
+        let user_agent = explanation.user_agent.expect("No user agent rule");
+        assert_eq!((user_agent.file, user_agent.index), (None, 1));
+
+        let explanation = parser.explain("curl/8.0.1");
+        let bot = explanation.bot.expect("No bot rule");
+        assert_eq!(bot.file.as_deref(), Some("bots.yaml"));
+    }
+}
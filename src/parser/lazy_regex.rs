@@ -0,0 +1,112 @@
+use std::sync::OnceLock;
+
+use regex::bytes::{Regex, RegexBuilder};
+
+/// A regex that is either compiled up front or on its first use.
+///
+/// The source pattern is kept around so the regex can be compiled from any
+/// thread the first time it is needed. A pattern that fails to compile never
+/// matches, and the error is reported by `validate`.
+#[derive(Debug, Clone)]
+pub(crate) struct LazyRegex {
+    pattern: String,
+    unicode: bool,
+    size_limit: Option<usize>,
+    regex: OnceLock<Result<Regex, regex::Error>>,
+}
+
+impl LazyRegex {
+    /// Creates the regex, compiling it immediately unless `lazy` is set.
+    pub(crate) fn new(
+        pattern: String,
+        unicode: bool,
+        size_limit: Option<usize>,
+        lazy: bool,
+    ) -> Result<Self, regex::Error> {
+        let regex = LazyRegex {
+            pattern,
+            unicode,
+            size_limit,
+            regex: OnceLock::new(),
+        };
+
+        if !lazy {
+            regex.validate()?;
+        }
+
+        Ok(regex)
+    }
+
+    pub(crate) fn as_str(&self) -> &str {
+        &self.pattern
+    }
+
+    /// Returns the compiled regex, compiling it first if necessary, or `None`
+    /// if the pattern is invalid.
+    #[inline]
+    pub(crate) fn get(&self) -> Option<&Regex> {
+        self.compiled().as_ref().ok()
+    }
+
+    /// Compiles the regex if it has not been already and reports whether the
+    /// pattern is valid.
+    pub(crate) fn validate(&self) -> Result<(), regex::Error> {
+        self.compiled().as_ref().map(|_| ()).map_err(Clone::clone)
+    }
+
+    #[cfg(test)]
+    pub(crate) fn is_compiled(&self) -> bool {
+        self.regex.get().is_some()
+    }
+
+    fn compiled(&self) -> &Result<Regex, regex::Error> {
+        self.regex.get_or_init(|| {
+            let mut builder = RegexBuilder::new(&self.pattern);
+            builder.unicode(self.unicode);
+            if let Some(size_limit) = self.size_limit {
+                builder.size_limit(size_limit);
+            }
+            builder.build()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Error, Parser, Section, UserAgentParser};
+
+    const REGEXES: &str = r"
+user_agent_parsers:
+  - regex: '(Firefox)/(\d+)\.(\d+)'
+  - regex: '(?<=Chrome)/(\d+)'
+os_parsers:
+  - regex: '(Linux)'
+device_parsers: []
+";
+
+    #[test]
+    fn lazy_compilation_defers_errors_to_validate() {
+        assert!(UserAgentParser::builder()
+            .build_from_bytes(REGEXES.as_bytes())
+            .is_err());
+
+        let parser = UserAgentParser::builder()
+            .with_lazy_compilation(true)
+            .build_from_bytes(REGEXES.as_bytes())
+            .expect("Parser creation failed");
+
+        let client = parser.parse("Mozilla/5.0 (X11; Linux x86_64) Firefox/115.0");
+        assert_eq!(client.user_agent.family, "Firefox");
+        assert_eq!(client.user_agent.major.as_deref(), Some("115"));
+        assert_eq!(client.os.family, "Linux");
+
+        let client = parser.parse("Chrome/114.0.0.0");
+        assert_eq!(client.user_agent.family, "Other");
+
+        parser.warm_up();
+        match parser.validate() {
+            Err(Error::Rule(e)) => assert_eq!(e.section, Section::UserAgent),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,196 @@
+use std::collections::HashMap;
+
+use aho_corasick::{AhoCorasick, MatchKind};
+use regex_syntax::hir::{
+    literal::{ExtractKind, Extractor},
+    Hir, HirKind,
+};
+
+/// Literals shorter than this are too common in user agent strings to rule
+/// anything out, so rules whose best literal is shorter are always evaluated.
+const MIN_LITERAL_LEN: usize = 2;
+
+/// A multi-pattern automaton over the literals each rule requires.
+///
+/// At build time every pattern is parsed and a set of literals is extracted
+/// such that any string the pattern matches must contain at least one of them.
+/// A single scan of the input then tells us which rules could possibly match,
+/// and the rest can be skipped without running their regexes.
+#[derive(Debug, Clone)]
+pub(crate) struct LiteralPrefilter {
+    automaton: AhoCorasick,
+    /// The rules requiring each literal in the automaton, by pattern id.
+    rules: Vec<Vec<usize>>,
+    /// Rules with no usable literal, which are candidates for every input.
+    unconditional: Vec<usize>,
+    len: usize,
+}
+
+/// The rules that survived the prefilter for a single input.
+pub(crate) struct Candidates {
+    bits: Vec<u64>,
+}
+
+impl Candidates {
+    #[inline]
+    pub(crate) fn contains(&self, rule: usize) -> bool {
+        self.bits[rule / 64] & (1 << (rule % 64)) != 0
+    }
+
+    /// Returns true if any rule in `start..end` is a candidate.
+    pub(crate) fn any_in(&self, start: usize, end: usize) -> bool {
+        (start..end).any(|rule| self.contains(rule))
+    }
+
+    #[inline]
+    fn insert(&mut self, rule: usize) {
+        self.bits[rule / 64] |= 1 << (rule % 64);
+    }
+}
+
+impl LiteralPrefilter {
+    /// Builds the prefilter for `patterns`, or returns `None` if none of them
+    /// have a literal worth searching for.
+    pub(crate) fn new<'p>(
+        patterns: impl IntoIterator<Item = &'p str>,
+        unicode: bool,
+    ) -> Option<Self> {
+        let mut literals: Vec<Vec<u8>> = Vec::new();
+        let mut ids: HashMap<Vec<u8>, usize> = HashMap::new();
+        let mut rules: Vec<Vec<usize>> = Vec::new();
+        let mut unconditional = Vec::new();
+        let mut len = 0;
+
+        for (rule, pattern) in patterns.into_iter().enumerate() {
+            len += 1;
+            let Some(required) = parse(pattern, unicode).and_then(|h| required(&h))
+            else {
+                unconditional.push(rule);
+                continue;
+            };
+
+            for literal in required {
+                let id = *ids.entry(literal.clone()).or_insert_with(|| {
+                    literals.push(literal);
+                    rules.push(Vec::new());
+                    literals.len() - 1
+                });
+                if rules[id].last() != Some(&rule) {
+                    rules[id].push(rule);
+                }
+            }
+        }
+
+        if literals.is_empty() {
+            return None;
+        }
+
+        let automaton = AhoCorasick::builder()
+            .match_kind(MatchKind::Standard)
+            .build(&literals)
+            .ok()?;
+
+        Some(LiteralPrefilter {
+            automaton,
+            rules,
+            unconditional,
+            len,
+        })
+    }
+
+    /// Scans `text` once and returns every rule that might match it.
+    pub(crate) fn candidates(&self, text: &[u8]) -> Candidates {
+        let mut candidates = Candidates {
+            bits: vec![0; (self.len + 63) / 64],
+        };
+        for &rule in &self.unconditional {
+            candidates.insert(rule);
+        }
+
+        let mut seen = vec![false; self.rules.len()];
+        for found in self.automaton.find_overlapping_iter(text) {
+            let id = found.pattern().as_usize();
+            if !seen[id] {
+                seen[id] = true;
+                for &rule in &self.rules[id] {
+                    candidates.insert(rule);
+                }
+            }
+        }
+
+        candidates
+    }
+}
+
+fn parse(pattern: &str, unicode: bool) -> Option<Hir> {
+    regex_syntax::ParserBuilder::new()
+        .unicode(unicode)
+        .utf8(false)
+        .build()
+        .parse(pattern)
+        .ok()
+}
+
+/// Returns a set of literals at least one of which appears in every match of
+/// `hir`, preferring the set whose shortest literal is the longest.
+fn required(hir: &Hir) -> Option<Vec<Vec<u8>>> {
+    match hir.kind() {
+        HirKind::Capture(capture) => required(&capture.sub),
+        HirKind::Concat(subs) => subs
+            .iter()
+            .filter_map(required)
+            .chain(prefixes(hir))
+            .max_by_key(|literals| {
+                (shortest(literals), std::cmp::Reverse(literals.len()))
+            }),
+        _ => prefixes(hir),
+    }
+}
+
+/// Every match of `hir` starts with one of its prefix literals, as long as the
+/// extracted sequence is finite.
+fn prefixes(hir: &Hir) -> Option<Vec<Vec<u8>>> {
+    let seq = Extractor::new().kind(ExtractKind::Prefix).extract(hir);
+    let literals = seq.literals()?;
+    if literals.is_empty() || seq.min_literal_len()? < MIN_LITERAL_LEN {
+        return None;
+    }
+    Some(literals.iter().map(|l| l.as_bytes().to_vec()).collect())
+}
+
+fn shortest(literals: &[Vec<u8>]) -> usize {
+    literals.iter().map(Vec::len).min().unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extracts_required_literals() {
+        let prefilter = LiteralPrefilter::new(
+            [
+                r"(Firefox)/(\d+)\.(\d+)",
+                r"\bCrOS [a-z0-9_]+ (\d+)",
+                r"(?i)(SM-\w+)",
+                r"^(\w+)/(\d+)",
+            ],
+            false,
+        )
+        .expect("prefilter should have literals");
+
+        assert_eq!(prefilter.unconditional, vec![3]);
+
+        let candidates =
+            prefilter.candidates(b"Mozilla/5.0 (X11; CrOS x86_64 14541.0.0)");
+        assert!(!candidates.contains(0));
+        assert!(candidates.contains(1));
+        assert!(!candidates.contains(2));
+        assert!(candidates.contains(3));
+
+        let candidates = prefilter.candidates(b"Dalvik/2.1.0 (Linux; sm-g991b Build)");
+        assert!(!candidates.contains(0));
+        assert!(!candidates.contains(1));
+        assert!(candidates.contains(2));
+    }
+}
@@ -0,0 +1,300 @@
+use std::sync::OnceLock;
+
+use regex::bytes::{RegexSet, RegexSetBuilder};
+
+use super::{lazy_regex::LazyRegex, literals::LiteralPrefilter, *};
+
+/// The number of patterns compiled into a single `RegexSet`. Keeping the sets
+/// small stops the lazy DFA behind each one from thrashing its cache on the
+/// larger sections of `regexes.yaml`.
+const CHUNK_SIZE: usize = 64;
+
+/// Matches the size limit used for the individual device and user agent
+/// regexes, so any pattern that compiles on its own also fits in a set.
+const SET_SIZE_LIMIT: usize = 20 * (1 << 20);
+
+/// Gives access to the regex a `Matcher` was built from, after flags have
+/// been applied and escapes cleaned up.
+pub(crate) trait Pattern {
+    fn regex(&self) -> &LazyRegex;
+}
+
+/// An ordered list of matchers for a single section of the regex file.
+///
+/// When the `RegexSet` prefilter is enabled every pattern is additionally
+/// compiled into a series of `RegexSet`s. A single pass over each set reports
+/// which rules match, so only those rules have to run their captures, and a
+/// string that matches nothing never touches the individual regexes at all.
+///
+/// The optional literal prefilter narrows this down further: rules whose
+/// required literals do not appear in the input are never evaluated, and sets
+/// containing no remaining candidates are skipped entirely.
+///
+/// With lazy compilation the prefilters are not built until `warm_up`, as they
+/// would compile every pattern in the section. Until then rules are tried in
+/// order, and each regex is compiled when it is first reached.
+#[derive(Debug, Clone)]
+pub(crate) struct Matchers<M> {
+    rules: Vec<M>,
+    unicode: bool,
+    regex_set: bool,
+    literal_prefilter: bool,
+    prefilters: OnceLock<Prefilters>,
+}
+
+#[derive(Debug, Clone)]
+struct Prefilters {
+    chunks: Vec<Chunk>,
+    literals: Option<LiteralPrefilter>,
+}
+
+/// A contiguous run of matchers covered by one `RegexSet`. The set is `None`
+/// if it could not be compiled, in which case the run is scanned linearly.
+#[derive(Debug, Clone)]
+struct Chunk {
+    start: usize,
+    end: usize,
+    set: Option<RegexSet>,
+}
+
+impl<M: Pattern> Matchers<M> {
+    pub(crate) fn new(matchers: Vec<M>, builder: &UserAgentParserBuilder) -> Self {
+        let list = Matchers {
+            rules: matchers,
+            unicode: builder.unicode,
+            regex_set: builder.regex_set,
+            literal_prefilter: builder.literal_prefilter,
+            prefilters: OnceLock::new(),
+        };
+
+        if !builder.lazy {
+            list.prefilters();
+        }
+
+        list
+    }
+
+    /// Compiles every regex and prefilter that has not been compiled yet.
+    pub(crate) fn warm_up(&self) {
+        for rule in &self.rules {
+            let _ = rule.regex().get();
+        }
+        self.prefilters();
+    }
+
+    /// Compiles every regex, returning the first error along with the index
+    /// of the rule that caused it.
+    pub(crate) fn validate(&self) -> Result<(), (usize, regex::Error)> {
+        for (i, rule) in self.rules.iter().enumerate() {
+            rule.regex().validate().map_err(|e| (i, e))?;
+        }
+        Ok(())
+    }
+
+    fn prefilters(&self) -> &Prefilters {
+        self.prefilters.get_or_init(|| {
+            let patterns: Vec<&str> = self
+                .rules
+                .iter()
+                .map(|rule| rule.regex().as_str())
+                .collect();
+
+            let mut chunks = Vec::new();
+            if self.regex_set {
+                for (i, window) in patterns.chunks(CHUNK_SIZE).enumerate() {
+                    build_chunks(window, i * CHUNK_SIZE, self.unicode, &mut chunks);
+                }
+            }
+
+            let literals = if self.literal_prefilter {
+                LiteralPrefilter::new(patterns, self.unicode)
+            } else {
+                None
+            };
+
+            Prefilters { chunks, literals }
+        })
+    }
+
+    /// Returns the result of the first matcher in the list that successfully
+    /// parses `text`.
+    pub(crate) fn parse<'a>(&self, text: &'a str) -> Option<M::Item>
+    where
+        M: SubParser<'a>,
+    {
+        self.find(text).map(|(_, item)| item)
+    }
+
+    /// Like `parse`, but also returns the index of the matcher that produced
+    /// the result.
+    pub(crate) fn find<'a>(&self, text: &'a str) -> Option<(usize, M::Item)>
+    where
+        M: SubParser<'a>,
+    {
+        let try_parse = |i: usize| self.rules[i].try_parse(text).map(|item| (i, item));
+        let Some(Prefilters { chunks, literals }) = self.prefilters.get() else {
+            return (0..self.rules.len()).find_map(try_parse);
+        };
+
+        let candidates = literals
+            .as_ref()
+            .map(|literals| literals.candidates(text.as_bytes()));
+        let is_candidate = |i: usize| candidates.as_ref().map_or(true, |c| c.contains(i));
+
+        if chunks.is_empty() {
+            return (0..self.rules.len())
+                .filter(|&i| is_candidate(i))
+                .find_map(try_parse);
+        }
+
+        chunks.iter().find_map(|chunk| {
+            if let Some(candidates) = &candidates {
+                if !candidates.any_in(chunk.start, chunk.end) {
+                    return None;
+                }
+            }
+
+            match &chunk.set {
+                Some(set) => set
+                    .matches(text.as_bytes())
+                    .iter()
+                    .find_map(|i| try_parse(chunk.start + i)),
+                None => (chunk.start..chunk.end)
+                    .filter(|&i| is_candidate(i))
+                    .find_map(try_parse),
+            }
+        })
+    }
+}
+
+impl<M> Matchers<M> {
+    pub(crate) fn get(&self, index: usize) -> Option<&M> {
+        self.rules.get(index)
+    }
+
+    pub(crate) fn empty() -> Self {
+        Matchers {
+            rules: vec![],
+            unicode: false,
+            regex_set: false,
+            literal_prefilter: false,
+            prefilters: OnceLock::new(),
+        }
+    }
+}
+
+/// Compiles `patterns` into a single `RegexSet`, splitting the run in half
+/// whenever the combined program would exceed the size limit.
+fn build_chunks(patterns: &[&str], start: usize, unicode: bool, out: &mut Vec<Chunk>) {
+    let set = RegexSetBuilder::new(patterns)
+        .unicode(unicode)
+        .size_limit(SET_SIZE_LIMIT)
+        .build();
+
+    match set {
+        Ok(set) => out.push(Chunk {
+            start,
+            end: start + patterns.len(),
+            set: Some(set),
+        }),
+        Err(regex::Error::CompiledTooBig(_)) if patterns.len() > 1 => {
+            let (left, right) = patterns.split_at(patterns.len() / 2);
+            build_chunks(left, start, unicode, out);
+            build_chunks(right, start + left.len(), unicode, out);
+        }
+        Err(_) => out.push(Chunk {
+            start,
+            end: start + patterns.len(),
+            set: None,
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fmt::Write;
+
+    use super::Pattern;
+    use crate::{Parser, UserAgentParser};
+
+    /// Enough rules to span several chunks, with the Firefox rules at the very
+    /// end and a later rule that is shadowed by an earlier one.
+    fn regexes() -> String {
+        let mut yaml = String::from("user_agent_parsers:\n");
+        for i in 0..150 {
+            writeln!(yaml, "  - regex: '(NoSuchAgent{i})/(\\d+)'").unwrap();
+        }
+        yaml.push_str("  - regex: '(Firefox)/(\\d+)\\.(\\d+)'\n");
+        yaml.push_str("  - regex: '(Firefox)'\n    family_replacement: 'Shadowed'\n");
+        yaml.push_str("os_parsers:\n  - regex: '(Linux)'\n");
+        yaml.push_str("device_parsers: []\n");
+        yaml
+    }
+
+    #[test]
+    fn prefilters_agree_with_linear_scan() {
+        let yaml = regexes();
+        let with_set = UserAgentParser::builder()
+            .build_from_bytes(yaml.as_bytes())
+            .expect("Parser creation failed");
+        let without_set = UserAgentParser::builder()
+            .with_regex_set_prefilter(false)
+            .build_from_bytes(yaml.as_bytes())
+            .expect("Parser creation failed");
+
+        let with_literals = UserAgentParser::builder()
+            .with_literal_prefilter(true)
+            .build_from_bytes(yaml.as_bytes())
+            .expect("Parser creation failed");
+        let only_literals = UserAgentParser::builder()
+            .with_regex_set_prefilter(false)
+            .with_literal_prefilter(true)
+            .build_from_bytes(yaml.as_bytes())
+            .expect("Parser creation failed");
+
+        for ua in [
+            "Mozilla/5.0 (X11; Linux x86_64) Gecko/20100101 Firefox/115.0",
+            "NoSuchAgent149/3",
+            "curl/8.0.1",
+            "",
+        ] {
+            let expected = without_set.parse(ua);
+            assert_eq!(with_set.parse(ua), expected);
+            assert_eq!(with_literals.parse(ua), expected);
+            assert_eq!(only_literals.parse(ua), expected);
+        }
+
+        let client = with_set.parse("NoSuchAgent7/42 Firefox/115.0");
+        assert_eq!(client.user_agent.family, "NoSuchAgent7");
+        assert_eq!(client.user_agent.major.as_deref(), Some("42"));
+        assert_eq!(client.os.family, "Other");
+    }
+
+    #[test]
+    fn lazy_parse_only_compiles_rules_it_reaches() {
+        let yaml = regexes();
+        let parser = UserAgentParser::builder()
+            .with_lazy_compilation(true)
+            .with_literal_prefilter(true)
+            .build_from_bytes(yaml.as_bytes())
+            .expect("Parser creation failed");
+        let compiled = |parser: &UserAgentParser| {
+            parser
+                .user_agent
+                .rules
+                .iter()
+                .filter(|rule| rule.regex().is_compiled())
+                .count()
+        };
+
+        let client = parser.parse("NoSuchAgent2/42");
+        assert_eq!(client.user_agent.family, "NoSuchAgent2");
+        assert_eq!(compiled(&parser), 3);
+        assert!(parser.user_agent.prefilters.get().is_none());
+
+        parser.warm_up();
+        assert_eq!(compiled(&parser), parser.user_agent.rules.len());
+        assert!(parser.user_agent.prefilters.get().is_some());
+        assert_eq!(parser.parse("NoSuchAgent2/42"), client);
+    }
+}
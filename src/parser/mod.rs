@@ -1,9 +1,14 @@
-use std::borrow::Cow;
+use std::{borrow::Cow, collections::HashMap, sync::Arc};
 
 use derive_more::{Display, From};
 use regex::Regex;
 
-use super::{client::Client, file::RegexFile, Parser, SubParser};
+use super::{
+    client::Client,
+    explain::{Explanation, Replacement},
+    file::RegexFile,
+    Parser, SubParser,
+};
 
 mod device;
 use super::{
@@ -19,8 +24,56 @@ use super::{
     user_agent::UserAgent,
 };
 
+mod engine;
+use super::{
+    engine::Engine, file::EngineParserEntry, parser::engine::Error as EngineError,
+};
+
+mod architecture;
+use super::{
+    architecture::Architecture, file::ArchitectureParserEntry,
+    parser::architecture::Error as ArchitectureError,
+};
+
+mod bot;
+use super::{
+    bot::{BotCategory, BotInfo},
+    file::BotParserEntry,
+    parser::bot::Error as BotError,
+};
+
 mod builder;
-use self::builder::UserAgentParserBuilder;
+pub use self::builder::UserAgentParserBuilder;
+
+pub mod codegen;
+use self::codegen::{
+    PrecompiledArchitectureEntry, PrecompiledBotEntry, PrecompiledDeviceEntry,
+    PrecompiledEngineEntry, PrecompiledOSEntry, PrecompiledRegexes,
+    PrecompiledUserAgentEntry,
+};
+
+mod explain;
+use self::explain::{replacement, Explain};
+
+mod lazy_regex;
+use self::lazy_regex::LazyRegex;
+
+mod batch;
+
+mod literals;
+
+mod matchers;
+use self::matchers::{Matchers, Pattern};
+
+mod overlay;
+use self::overlay::{find_location, locate, Origin, Origins, Overlay, Rule};
+pub use self::overlay::{OverlayError, Precedence};
+
+mod rule_error;
+pub use self::rule_error::{Location, RuleError, Section};
+
+mod template;
+use self::template::Template;
 
 #[derive(Debug, Display, From)]
 pub enum Error {
@@ -29,69 +82,306 @@ pub enum Error {
     Device(DeviceError),
     OS(OSError),
     UserAgent(UserAgentError),
+    Engine(EngineError),
+    Architecture(ArchitectureError),
+    Bot(BotError),
+    Overlay(OverlayError),
+    Rule(RuleError),
+    /// Overlays were set on a builder used with `build_from_precompiled`.
+    #[display(fmt = "overlays cannot be applied to precompiled regexes")]
+    #[from(ignore)]
+    PrecompiledOverlay,
 }
 
-impl std::error::Error for Error {}
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        // Every variant displays as the error it wraps, so the source is that
+        // error's source rather than the error itself.
+        match self {
+            Error::IO(e) => e.source(),
+            Error::Yaml(e) => e.source(),
+            Error::Device(e) => e.source(),
+            Error::OS(e) => e.source(),
+            Error::UserAgent(e) => e.source(),
+            Error::Engine(e) => e.source(),
+            Error::Architecture(e) => e.source(),
+            Error::Bot(e) => e.source(),
+            Error::Overlay(e) => e.source(),
+            Error::Rule(e) => e.source(),
+            Error::PrecompiledOverlay => None,
+        }
+    }
+}
+
+impl Error {
+    /// Returns the rule this error is about, if any, looking through
+    /// `Error::Overlay`.
+    #[must_use]
+    pub fn rule(&self) -> Option<&RuleError> {
+        match self {
+            Error::Rule(rule) => Some(rule),
+            Error::Overlay(e) => e.error.rule(),
+            _ => None,
+        }
+    }
+}
+
+/// The version of uap-core that the embedded `regexes.yaml` was taken from.
+#[cfg(feature = "embedded-regexes")]
+pub const UAP_CORE_VERSION: &str = env!("UAP_CORE_VERSION");
+
+#[cfg(feature = "embedded-regexes")]
+static EMBEDDED_REGEXES: &[u8] = include_bytes!("../core/regexes.yaml");
 
 /// Handles the actual parsing of a user agent string by delegating to
 /// the respective `SubParser`
 #[derive(Debug, Clone)]
 pub struct UserAgentParser {
-    device_matchers: Vec<device::Matcher>,
-    os_matchers: Vec<os::Matcher>,
-    user_agent_matchers: Vec<user_agent::Matcher>,
+    device: Matchers<device::Matcher>,
+    os: Matchers<os::Matcher>,
+    user_agent: Matchers<user_agent::Matcher>,
+    engine: Matchers<engine::Matcher>,
+    architecture: Matchers<architecture::Matcher>,
+    bot: Matchers<bot::Matcher>,
+    /// Whether agents the device rules mark as a `Spider` count as bots, which
+    /// is the case unless bot classification is disabled.
+    spider_bots: bool,
+    origins: Origins,
+    /// The rules lenient loading left out
+    skipped: Arc<[Error]>,
 }
 
 impl Parser for UserAgentParser {
     /// Returns the full `Client` info when given a user agent string
     fn parse<'a>(&self, user_agent: &'a str) -> Client<'a> {
+        let device = self.parse_device(user_agent);
+        let os = self.parse_os(user_agent);
+        let engine = self.parse_engine(user_agent);
+        let architecture = self.parse_architecture(user_agent);
+        let bot = self.bot.parse(user_agent);
+        let user_agent = self.parse_user_agent(user_agent);
+        let bot = bot.or_else(|| self.spider_bot(&device, &user_agent));
+
         Client {
-            device: self.parse_device(user_agent),
-            os: self.parse_os(user_agent),
-            user_agent: self.parse_user_agent(user_agent),
+            device,
+            os,
+            user_agent,
+            engine,
+            architecture,
+            bot,
         }
     }
 
     /// Returns just the `Device` info when given a user agent string
     fn parse_device<'a>(&self, user_agent: &'a str) -> Device<'a> {
-        self.device_matchers
-            .iter()
-            .find_map(|matcher| matcher.try_parse(user_agent))
-            .unwrap_or_default()
+        self.device.parse(user_agent).unwrap_or_default()
     }
 
     /// Returns just the `OS` info when given a user agent string
     fn parse_os<'a>(&self, user_agent: &'a str) -> OS<'a> {
-        self.os_matchers
-            .iter()
-            .find_map(|matcher| matcher.try_parse(user_agent))
-            .unwrap_or_default()
+        self.os.parse(user_agent).unwrap_or_default()
     }
 
     /// Returns just the `UserAgent` info when given a user agent string
     fn parse_user_agent<'a>(&self, user_agent: &'a str) -> UserAgent<'a> {
-        self.user_agent_matchers
-            .iter()
-            .find_map(|matcher| matcher.try_parse(user_agent))
-            .unwrap_or_default()
+        self.user_agent.parse(user_agent).unwrap_or_default()
+    }
+
+    /// Returns just the `Engine` info when given a user agent string
+    fn parse_engine<'a>(&self, user_agent: &'a str) -> Engine<'a> {
+        self.engine.parse(user_agent).unwrap_or_default()
+    }
+
+    /// Returns just the `Architecture` info when given a user agent string
+    fn parse_architecture<'a>(&self, user_agent: &'a str) -> Architecture<'a> {
+        self.architecture.parse(user_agent).unwrap_or_default()
+    }
+
+    /// Returns just the `BotInfo` when given a user agent string. Agents that
+    /// no bot rule matches are still reported if the device rules classify
+    /// them as a `Spider`.
+    fn parse_bot<'a>(&self, user_agent: &'a str) -> Option<BotInfo<'a>> {
+        self.bot.parse(user_agent).or_else(|| {
+            self.spider_bot(
+                &self.parse_device(user_agent),
+                &self.parse_user_agent(user_agent),
+            )
+        })
     }
 }
 
 impl UserAgentParser {
-    #[must_use]
     pub fn builder() -> UserAgentParserBuilder {
         UserAgentParserBuilder::new()
     }
 
+    /// Parses a user agent string like `parse`, but also reports which rule,
+    /// and from which file, produced each part of the result, what it
+    /// captured, and which replacements it applied. Useful for tracking down
+    /// why a string was classified the way it was.
+    ///
+    /// ```rust
+    /// # use uaparser::*;
+    /// let regexes = br"
+    /// user_agent_parsers:
+    ///   - regex: '(Firefox)/(\d+)\.(\d+)'
+    ///     family_replacement: 'Firefox Desktop'
+    /// os_parsers: []
+    /// device_parsers: []
+    /// ";
+    /// let parser = UserAgentParser::from_bytes(regexes).unwrap();
+    /// let explanation = parser.explain("Mozilla/5.0 Firefox/115.0");
+    ///
+    /// let rule = explanation.user_agent.unwrap();
+    /// assert_eq!((rule.file, rule.index), (None, 0));
+    /// assert_eq!(rule.captures[1], Some("Firefox"));
+    /// assert_eq!(rule.replacements[0].value.as_deref(), Some("Firefox Desktop"));
+    /// assert!(explanation.os.is_none());
+    /// ```
+    #[must_use]
+    pub fn explain<'a>(&self, user_agent: &'a str) -> Explanation<'a> {
+        let origins = &self.origins;
+        let device = explain::explain(&self.device, &origins.device, user_agent);
+        let os = explain::explain(&self.os, &origins.os, user_agent);
+        let engine = explain::explain(&self.engine, &origins.engine, user_agent);
+        let architecture =
+            explain::explain(&self.architecture, &origins.architecture, user_agent);
+        let bot = explain::explain(&self.bot, &origins.bot, user_agent);
+        let user_agent =
+            explain::explain(&self.user_agent, &origins.user_agent, user_agent);
+
+        let (device, device_rule) = device.unzip();
+        let (os, os_rule) = os.unzip();
+        let (user_agent, user_agent_rule) = user_agent.unzip();
+        let (engine, engine_rule) = engine.unzip();
+        let (architecture, architecture_rule) = architecture.unzip();
+        let (bot, bot_rule) = bot.unzip();
+
+        let device = device.unwrap_or_default();
+        let user_agent = user_agent.unwrap_or_default();
+        let bot = bot.or_else(|| self.spider_bot(&device, &user_agent));
+
+        Explanation {
+            client: Client {
+                device,
+                os: os.unwrap_or_default(),
+                user_agent,
+                engine: engine.unwrap_or_default(),
+                architecture: architecture.unwrap_or_default(),
+                bot,
+            },
+            device: device_rule,
+            os: os_rule,
+            user_agent: user_agent_rule,
+            engine: engine_rule,
+            architecture: architecture_rule,
+            bot: bot_rule,
+        }
+    }
+
+    fn spider_bot<'a>(
+        &self,
+        device: &Device<'_>,
+        user_agent: &UserAgent<'a>,
+    ) -> Option<BotInfo<'a>> {
+        if self.spider_bots {
+            bot::from_spider(device, user_agent)
+        } else {
+            None
+        }
+    }
+
+    /// Compiles every regex that has not been compiled yet. This is only
+    /// useful for parsers built with lazy compilation enabled, for services
+    /// that would rather pay the compilation cost up front than on the first
+    /// requests. It also builds the prefilters, which lazy parsers otherwise
+    /// go without. Patterns that fail to compile are skipped; use `validate`
+    /// to find them.
+    pub fn warm_up(&self) {
+        self.device.warm_up();
+        self.os.warm_up();
+        self.user_agent.warm_up();
+        self.engine.warm_up();
+        self.architecture.warm_up();
+        self.bot.warm_up();
+    }
+
+    /// Compiles every regex and reports the first one that is invalid. With
+    /// lazy compilation enabled this is the only way to surface the errors
+    /// that building the parser would otherwise have returned.
+    pub fn validate(&self) -> Result<(), Error> {
+        let origins = &self.origins;
+        validate(&self.device, origins, Section::Device, DeviceError::Regex)?;
+        validate(&self.os, origins, Section::OS, OSError::Regex)?;
+        validate(
+            &self.user_agent,
+            origins,
+            Section::UserAgent,
+            UserAgentError::Regex,
+        )?;
+        validate(&self.engine, origins, Section::Engine, EngineError::Regex)?;
+        validate(
+            &self.architecture,
+            origins,
+            Section::Architecture,
+            ArchitectureError::Regex,
+        )?;
+        validate(&self.bot, origins, Section::Bot, BotError::Regex)?;
+        Ok(())
+    }
+
+    /// Returns the rules that were left out because they failed to compile,
+    /// see `UserAgentParserBuilder::with_lenient_loading`. Each one is an
+    /// `Error::Rule`, or an `Error::Overlay` wrapping one.
+    #[must_use]
+    pub fn skipped_rules(&self) -> &[Error] {
+        &self.skipped
+    }
+
+    /// Returns how many rules of `section` were left out because they failed
+    /// to compile.
+    #[must_use]
+    pub fn skipped(&self, section: Section) -> usize {
+        self.skipped
+            .iter()
+            .filter(|e| e.rule().is_some_and(|rule| rule.section == section))
+            .count()
+    }
+
+    /// Constructs a `UserAgentParser` from the `regexes.yaml` bundled with this
+    /// crate, see `UAP_CORE_VERSION` for the release it was taken from.
+    ///
+    /// ```rust
+    /// # use uaparser::*;
+    /// let parser = UserAgentParser::default_embedded();
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if the bundled file fails to load, which would mean the crate
+    /// itself was built from a broken uap-core checkout.
+    #[cfg(feature = "embedded-regexes")]
+    #[must_use]
+    pub fn default_embedded() -> UserAgentParser {
+        UserAgentParser::from_bytes(EMBEDDED_REGEXES)
+            .expect("embedded regexes.yaml should always be valid")
+    }
+
+    #[cfg(feature = "embedded-regexes")]
+    fn _build_embedded(
+        builder: &UserAgentParserBuilder,
+    ) -> Result<UserAgentParser, Error> {
+        Self::_build_from_bytes(EMBEDDED_REGEXES, builder)
+    }
+
     /// Attempts to construct a `UserAgentParser` from the path to a file
     pub fn from_yaml(path: &str) -> Result<UserAgentParser, Error> {
-        let file = std::fs::File::open(path)?;
-        UserAgentParser::from_file(file)
+        Self::_build_from_yaml(path, &UserAgentParserBuilder::new())
     }
 
     fn _build_from_yaml(
         path: &str,
-        builder: UserAgentParserBuilder,
+        builder: &UserAgentParserBuilder,
     ) -> Result<UserAgentParser, Error> {
         let file = std::fs::File::open(path)?;
         Self::_build_from_file(file, builder)
@@ -108,132 +398,457 @@ impl UserAgentParser {
     /// let parser = UserAgentParser::from_bytes(regexes);
     /// ```
     pub fn from_bytes(bytes: &[u8]) -> Result<UserAgentParser, Error> {
-        let regex_file: RegexFile = serde_yaml::from_slice(bytes)?;
-        Self::try_from(regex_file)
+        Self::_build_from_bytes(bytes, &UserAgentParserBuilder::new())
     }
 
     fn _build_from_bytes(
         bytes: &[u8],
-        builder: UserAgentParserBuilder,
+        builder: &UserAgentParserBuilder,
     ) -> Result<UserAgentParser, Error> {
         let regex_file: RegexFile = serde_yaml::from_slice(bytes)?;
-        Self::_try_from(
-            regex_file,
-            builder.device,
-            builder.os,
-            builder.user_agent,
-            builder.unicode,
-        )
+        Self::_try_from_yaml(regex_file, Some(bytes), builder)
     }
 
     /// Attempts to construct a `UserAgentParser` from a reference to an open
     /// `File`. This `File` should be a the `regexes.yaml` depended on by
     /// all the various implementations of the UA Parser library.
+    ///
+    /// The file is read again from the start only if a rule fails to
+    /// compile, to report the line it is on.
     pub fn from_file(file: std::fs::File) -> Result<UserAgentParser, Error> {
-        let regex_file: RegexFile = serde_yaml::from_reader(file)?;
-        Self::try_from(regex_file)
+        Self::_build_from_file(file, &UserAgentParserBuilder::new())
     }
 
     fn _build_from_file(
-        file: std::fs::File,
-        builder: UserAgentParserBuilder,
+        mut file: std::fs::File,
+        builder: &UserAgentParserBuilder,
     ) -> Result<UserAgentParser, Error> {
-        let regex_file: RegexFile = serde_yaml::from_reader(file)?;
-        Self::_try_from(
-            regex_file,
-            builder.device,
-            builder.os,
-            builder.user_agent,
-            builder.unicode,
-        )
+        let regex_file: RegexFile = serde_yaml::from_reader(&file)?;
+        let result = Self::_compile(regex_file, builder);
+
+        // The YAML is only read again to point a rule that failed to compile
+        // at its line, so a file that loads cleanly is read once.
+        let failed = match &result {
+            Ok(parser) => parser.skipped.iter().any(|e| matches!(e, Error::Rule(_))),
+            Err(e) => matches!(e, Error::Rule(_)),
+        };
+        let mut yaml = Vec::new();
+        let reread = failed
+            && std::io::Seek::rewind(&mut file).is_ok()
+            && std::io::Read::read_to_end(&mut file, &mut yaml).is_ok();
+        Self::_locate(result, reread.then_some(&yaml[..]), builder)
+    }
+
+    /// Attempts to construct a `UserAgentParser` from the output of
+    /// `codegen::generate`, skipping YAML deserialization and pattern cleanup.
+    pub fn from_precompiled(
+        regexes: &PrecompiledRegexes,
+    ) -> Result<UserAgentParser, Error> {
+        Self::_build_from_precompiled(regexes, &UserAgentParserBuilder::new())
+    }
+
+    fn _build_from_precompiled(
+        regexes: &PrecompiledRegexes,
+        builder: &UserAgentParserBuilder,
+    ) -> Result<UserAgentParser, Error> {
+        if let Some(overlay) = builder.overlays.first() {
+            return Err(Error::Overlay(OverlayError {
+                name: overlay.name.clone(),
+                error: Box::new(Error::PrecompiledOverlay),
+            }));
+        }
+
+        let unicode = builder.unicode;
+        let lazy = builder.lazy;
+        let (bundled, origins) = bundled_rules(regexes, builder);
+        let mut compiler = Compiler::new(builder, origins);
+
+        let device = if builder.device {
+            let matchers =
+                compiler.compile(Section::Device, regexes.device_parsers, |entry| {
+                    device::Matcher::from_precompiled(entry, unicode, lazy)
+                })?;
+            Matchers::new(matchers, builder)
+        } else {
+            Matchers::empty()
+        };
+
+        let os = if builder.os {
+            let matchers =
+                compiler.compile(Section::OS, regexes.os_parsers, |entry| {
+                    os::Matcher::from_precompiled(entry, unicode, lazy)
+                })?;
+            Matchers::new(matchers, builder)
+        } else {
+            Matchers::empty()
+        };
+
+        let user_agent = if builder.user_agent {
+            let matchers = compiler.compile(
+                Section::UserAgent,
+                regexes.user_agent_parsers,
+                |entry| user_agent::Matcher::from_precompiled(entry, unicode, lazy),
+            )?;
+            Matchers::new(matchers, builder)
+        } else {
+            Matchers::empty()
+        };
+
+        let engine = if builder.engine {
+            let mut matchers =
+                compiler.compile(Section::Engine, regexes.engine_parsers, |entry| {
+                    engine::Matcher::from_precompiled(entry, unicode, lazy)
+                })?;
+            matchers.extend(compiler.compile(
+                Section::Engine,
+                bundled.engine_parsers,
+                |entry| engine::Matcher::try_from(entry, unicode, lazy),
+            )?);
+            Matchers::new(matchers, builder)
+        } else {
+            Matchers::empty()
+        };
+
+        let architecture = if builder.architecture {
+            let mut matchers = compiler.compile(
+                Section::Architecture,
+                regexes.architecture_parsers,
+                |entry| architecture::Matcher::from_precompiled(entry, unicode, lazy),
+            )?;
+            matchers.extend(compiler.compile(
+                Section::Architecture,
+                bundled.architecture_parsers,
+                |entry| architecture::Matcher::try_from(entry, unicode, lazy),
+            )?);
+            Matchers::new(matchers, builder)
+        } else {
+            Matchers::empty()
+        };
+
+        let bot = if builder.bot {
+            let mut matchers =
+                compiler.compile(Section::Bot, regexes.bot_parsers, |entry| {
+                    bot::Matcher::from_precompiled(entry, unicode, lazy)
+                })?;
+            matchers.extend(compiler.compile(
+                Section::Bot,
+                bundled.bot_parsers,
+                |entry| bot::Matcher::try_from(entry, unicode, lazy),
+            )?);
+            Matchers::new(matchers, builder)
+        } else {
+            Matchers::empty()
+        };
+
+        let (origins, skipped) = compiler.finish();
+        Ok(UserAgentParser {
+            device,
+            os,
+            user_agent,
+            engine,
+            architecture,
+            bot,
+            spider_bots: builder.bot,
+            origins,
+            skipped,
+        })
     }
 
     pub fn try_from(regex_file: RegexFile) -> Result<UserAgentParser, Error> {
-        Self::_try_from(regex_file, true, true, true, true)
+        Self::_try_from(regex_file, &UserAgentParserBuilder::new())
     }
 
     fn _try_from(
         regex_file: RegexFile,
-        device: bool,
-        os: bool,
-        user_agent: bool,
-        unicode: bool,
+        builder: &UserAgentParserBuilder,
+    ) -> Result<UserAgentParser, Error> {
+        Self::_try_from_yaml(regex_file, None, builder)
+    }
+
+    /// Builds the parser from `regex_file`, which was deserialized from
+    /// `yaml` if given, so rules that fail to compile can be pointed out
+    /// there.
+    fn _try_from_yaml(
+        regex_file: RegexFile,
+        yaml: Option<&[u8]>,
+        builder: &UserAgentParserBuilder,
+    ) -> Result<UserAgentParser, Error> {
+        Self::_locate(Self::_compile(regex_file, builder), yaml, builder)
+    }
+
+    /// Points the rules that failed to compile in `result` at where they
+    /// start in `yaml`, or in the overlay that defined them.
+    fn _locate(
+        result: Result<UserAgentParser, Error>,
+        yaml: Option<&[u8]>,
+        builder: &UserAgentParserBuilder,
     ) -> Result<UserAgentParser, Error> {
-        let device_matchers = if device {
-            let mut matchers = Vec::with_capacity(regex_file.device_parsers.len());
-            for parser in regex_file.device_parsers {
-                matchers.push(device::Matcher::try_from(parser, unicode)?);
+        let overlays = &builder.overlays;
+        let mut parser = result.map_err(|mut e| {
+            find_location(&mut e, yaml, overlays);
+            e
+        })?;
+        if let Some(skipped) = Arc::get_mut(&mut parser.skipped) {
+            for error in skipped {
+                find_location(error, yaml, overlays);
             }
-            matchers
+        }
+        Ok(parser)
+    }
+
+    fn _compile(
+        regex_file: RegexFile,
+        builder: &UserAgentParserBuilder,
+    ) -> Result<UserAgentParser, Error> {
+        let unicode = builder.unicode;
+        let lazy = builder.lazy;
+        // The bundled rules go first, so the user's overlays apply on top.
+        let bundled = bundled_overlays(builder);
+        let overlays: Vec<_> = bundled.iter().chain(&builder.overlays).collect();
+        let (regex_file, origins) = overlay::apply(regex_file, &overlays)?;
+        let mut compiler = Compiler::new(builder, origins);
+
+        let device = if builder.device {
+            let matchers = compiler.compile(
+                Section::Device,
+                regex_file.device_parsers,
+                |entry| device::Matcher::try_from(entry, unicode, lazy),
+            )?;
+            Matchers::new(matchers, builder)
         } else {
-            vec![]
+            Matchers::empty()
         };
 
-        let os_matchers = if os {
-            let mut matchers = Vec::with_capacity(regex_file.os_parsers.len());
-            for parser in regex_file.os_parsers {
-                matchers.push(os::Matcher::try_from(parser, unicode)?);
-            }
-            matchers
+        let os = if builder.os {
+            let matchers =
+                compiler.compile(Section::OS, regex_file.os_parsers, |entry| {
+                    os::Matcher::try_from(entry, unicode, lazy)
+                })?;
+            Matchers::new(matchers, builder)
         } else {
-            vec![]
+            Matchers::empty()
         };
 
-        let user_agent_matchers = if user_agent {
-            let mut matchers = Vec::with_capacity(regex_file.user_agent_parsers.len());
-            for parser in regex_file.user_agent_parsers {
-                matchers.push(user_agent::Matcher::try_from(parser, unicode)?);
-            }
-            matchers
+        let user_agent = if builder.user_agent {
+            let matchers = compiler.compile(
+                Section::UserAgent,
+                regex_file.user_agent_parsers,
+                |entry| user_agent::Matcher::try_from(entry, unicode, lazy),
+            )?;
+            Matchers::new(matchers, builder)
+        } else {
+            Matchers::empty()
+        };
+
+        let engine = if builder.engine {
+            let matchers = compiler.compile(
+                Section::Engine,
+                regex_file.engine_parsers,
+                |entry| engine::Matcher::try_from(entry, unicode, lazy),
+            )?;
+            Matchers::new(matchers, builder)
+        } else {
+            Matchers::empty()
+        };
+
+        let architecture = if builder.architecture {
+            let matchers = compiler.compile(
+                Section::Architecture,
+                regex_file.architecture_parsers,
+                |entry| architecture::Matcher::try_from(entry, unicode, lazy),
+            )?;
+            Matchers::new(matchers, builder)
         } else {
-            vec![]
+            Matchers::empty()
         };
 
+        let bot = if builder.bot {
+            let matchers =
+                compiler.compile(Section::Bot, regex_file.bot_parsers, |entry| {
+                    bot::Matcher::try_from(entry, unicode, lazy)
+                })?;
+            Matchers::new(matchers, builder)
+        } else {
+            Matchers::empty()
+        };
+
+        let (origins, skipped) = compiler.finish();
         Ok(UserAgentParser {
-            device_matchers,
-            os_matchers,
-            user_agent_matchers,
+            device,
+            os,
+            user_agent,
+            engine,
+            architecture,
+            bot,
+            spider_bots: builder.bot,
+            origins,
+            skipped,
         })
     }
 }
 
-#[inline]
-fn none_if_empty<T: AsRef<str>>(s: T) -> Option<T> {
-    if s.as_ref().is_empty() {
-        None
-    } else {
-        Some(s)
+/// The rule files bundled with this crate that `builder` turns on, as
+/// overlays appended to the base file.
+fn bundled_overlays(builder: &UserAgentParserBuilder) -> Vec<Overlay> {
+    let mut overlays = Vec::new();
+    if builder.engine && builder.builtin_engines {
+        let regex_file = RegexFile {
+            engine_parsers: engine::builtin_rules(),
+            ..RegexFile::default()
+        };
+        overlays.push(Overlay::bundled(engine::BUILTIN_NAME, regex_file));
+    }
+    if builder.architecture && builder.builtin_architectures {
+        let regex_file = RegexFile {
+            architecture_parsers: architecture::builtin_rules(),
+            ..RegexFile::default()
+        };
+        overlays.push(Overlay::bundled(architecture::BUILTIN_NAME, regex_file));
     }
+    if builder.bot && builder.builtin_bots {
+        let regex_file = RegexFile {
+            bot_parsers: bot::builtin_rules(),
+            ..RegexFile::default()
+        };
+        overlays.push(Overlay::bundled(bot::BUILTIN_NAME, regex_file));
+    }
+    overlays
 }
 
-#[inline]
-fn has_group(replacement: &str) -> bool {
-    replacement.contains('$')
+/// The bundled rules `builder` turns on, which are compiled after the
+/// precompiled `regexes`, along with the origins of both.
+fn bundled_rules(
+    regexes: &PrecompiledRegexes,
+    builder: &UserAgentParserBuilder,
+) -> (RegexFile, Origins) {
+    let mut bundled = RegexFile::default();
+    let mut origins = Origins::default();
+    if builder.engine && builder.builtin_engines {
+        bundled.engine_parsers = engine::builtin_rules();
+        origins.bundled(
+            Section::Engine,
+            regexes.engine_parsers.len(),
+            engine::BUILTIN_NAME,
+            bundled.engine_parsers.len(),
+        );
+    }
+    if builder.architecture && builder.builtin_architectures {
+        bundled.architecture_parsers = architecture::builtin_rules();
+        origins.bundled(
+            Section::Architecture,
+            regexes.architecture_parsers.len(),
+            architecture::BUILTIN_NAME,
+            bundled.architecture_parsers.len(),
+        );
+    }
+    if builder.bot && builder.builtin_bots {
+        bundled.bot_parsers = bot::builtin_rules();
+        origins.bundled(
+            Section::Bot,
+            regexes.bot_parsers.len(),
+            bot::BUILTIN_NAME,
+            bundled.bot_parsers.len(),
+        );
+    }
+    (bundled, origins)
+}
+
+/// Compiles the rules of each section of a parser, tracking where they were
+/// defined and, with lenient loading, which ones had to be left out.
+struct Compiler {
+    lenient: bool,
+    origins: Origins,
+    /// The origins of the rules that compiled, when loading leniently
+    kept: Origins,
+    skipped: Vec<Error>,
+    /// How many rules of each section were compiled so far, as a section may
+    /// be compiled in parts
+    compiled: HashMap<Section, usize>,
+}
+
+impl Compiler {
+    fn new(builder: &UserAgentParserBuilder, origins: Origins) -> Self {
+        Compiler {
+            lenient: builder.lenient,
+            origins,
+            kept: Origins::default(),
+            skipped: Vec::new(),
+            compiled: HashMap::new(),
+        }
+    }
+
+    /// Compiles the rules of `section` with `try_from`. The first one that
+    /// fails is reported as a `RuleError`, unless loading is lenient, in which
+    /// case every rule that fails is left out and recorded.
+    fn compile<E: Rule, M, Err: Into<Error>>(
+        &mut self,
+        section: Section,
+        entries: impl IntoIterator<Item = E>,
+        try_from: impl Fn(E) -> Result<M, Err>,
+    ) -> Result<Vec<M>, Error> {
+        let origins = self.origins.get(section);
+        let compiled = self.compiled.entry(section).or_default();
+        let entries = entries.into_iter();
+        let mut matchers = Vec::with_capacity(entries.size_hint().0);
+        for entry in entries {
+            let i = *compiled;
+            *compiled += 1;
+            let pattern = entry.pattern().to_owned();
+            match try_from(entry) {
+                Ok(matcher) => {
+                    matchers.push(matcher);
+                    if self.lenient {
+                        self.kept.get_mut(section).push(Origin::of(origins, i));
+                    }
+                }
+                Err(e) => {
+                    let error = locate(origins, section, i, &pattern, e);
+                    if !self.lenient {
+                        return Err(error);
+                    }
+                    self.skipped.push(error);
+                }
+            }
+        }
+        Ok(matchers)
+    }
+
+    /// Returns the origins of the compiled rules and the rules left out.
+    fn finish(self) -> (Origins, Arc<[Error]>) {
+        let origins = if self.lenient {
+            self.kept
+        } else {
+            self.origins
+        };
+        (origins, self.skipped.into())
+    }
+}
+
+/// Compiles every regex of `matchers`, reporting the first invalid one as a
+/// `RuleError`.
+fn validate<M: Pattern, E: Into<Error>>(
+    matchers: &Matchers<M>,
+    origins: &Origins,
+    section: Section,
+    error: fn(regex::Error) -> E,
+) -> Result<(), Error> {
+    matchers.validate().map_err(|(i, e)| {
+        let pattern = matchers.get(i).map_or("", |rule| rule.regex().as_str());
+        locate(origins.get(section), section, i, pattern, error(e))
+    })
 }
 
 #[inline]
-fn replace_cow<'a>(
-    replacement: &str,
-    replacement_has_group: bool,
-    captures: &regex::bytes::Captures,
-) -> Cow<'a, str> {
-    if replacement_has_group && captures.len() > 0 {
-        let mut target = vec![];
-        let raw_replacement = replacement.as_bytes();
-        captures.expand(raw_replacement, &mut target);
-        std::str::from_utf8(&target)
-            // What is the behavior if we can't parse a string???
-            .map_or_else(
-                |_| Cow::Owned(replacement.to_owned()),
-                |s| Cow::Owned(s.trim().to_owned()),
-            )
+fn none_if_empty<T: AsRef<str>>(s: T) -> Option<T> {
+    if s.as_ref().is_empty() {
+        None
     } else {
-        Cow::Owned(replacement.to_owned())
+        Some(s)
     }
 }
 
 #[inline]
-fn match_to_str(m: regex::bytes::Match) -> Option<&str> {
+fn match_to_str(m: regex::bytes::Match<'_>) -> Option<&str> {
     std::str::from_utf8(m.as_bytes()).ok()
 }
 
@@ -5,132 +5,122 @@ pub enum Error {
     Regex(regex::Error),
 }
 
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Regex(e) => e.source(),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
-#[allow(clippy::struct_excessive_bools)]
 pub struct Matcher {
-    regex: regex::bytes::Regex,
-    os_replacement: Option<String>,
-    os_v1_replacement: Option<String>,
-    os_v2_replacement: Option<String>,
-    os_v3_replacement: Option<String>,
-    os_replacement_has_group: bool,
-    os_v1_replacement_has_group: bool,
-    os_v2_replacement_has_group: bool,
-    os_v3_replacement_has_group: bool,
+    regex: LazyRegex,
+    os_replacement: Option<Template>,
+    os_v1_replacement: Option<Template>,
+    os_v2_replacement: Option<Template>,
+    os_v3_replacement: Option<Template>,
+    os_v4_replacement: Option<Template>,
 }
 
 impl<'a> SubParser<'a> for Matcher {
     type Item = OS<'a>;
 
     fn try_parse(&self, text: &'a str) -> Option<Self::Item> {
-        if !self.regex.is_match(text.as_bytes()) {
+        let regex = self.regex.get()?;
+        if !regex.is_match(text.as_bytes()) {
             return None;
         }
 
-        if let Some(captures) = self.regex.captures(text.as_bytes()) {
-            let family: Cow<'a, str> = if let Some(os_replacement) = &self.os_replacement
-            {
-                replace_cow(os_replacement, self.os_replacement_has_group, &captures)
-            } else {
-                captures
-                    .get(1)
-                    .and_then(match_to_str)
-                    .and_then(none_if_empty)
-                    .map(Cow::Borrowed)?
-            };
+        let captures = regex.captures(text.as_bytes())?;
 
-            let major: Option<Cow<'a, str>> =
-                if let Some(os_v1_replacement) = &self.os_v1_replacement {
-                    none_if_empty(replace_cow(
-                        os_v1_replacement,
-                        self.os_v1_replacement_has_group,
-                        &captures,
-                    ))
-                } else {
-                    captures
-                        .get(2)
-                        .and_then(match_to_str)
-                        .and_then(none_if_empty)
-                        .map(Cow::Borrowed)
-                };
+        let family: Cow<'a, str> = match &self.os_replacement {
+            Some(os_replacement) => os_replacement.expand(&captures),
+            None => template::capture(&captures, 1)?,
+        };
 
-            let minor: Option<Cow<'a, str>> =
-                if let Some(os_v2_replacement) = &self.os_v2_replacement {
-                    none_if_empty(replace_cow(
-                        os_v2_replacement,
-                        self.os_v2_replacement_has_group,
-                        &captures,
-                    ))
-                } else {
-                    captures
-                        .get(3)
-                        .and_then(match_to_str)
-                        .and_then(none_if_empty)
-                        .map(Cow::Borrowed)
-                };
-
-            let patch: Option<Cow<'a, str>> =
-                if let Some(os_v3_replacement) = &self.os_v3_replacement {
-                    none_if_empty(replace_cow(
-                        os_v3_replacement,
-                        self.os_v3_replacement_has_group,
-                        &captures,
-                    ))
-                } else {
-                    captures
-                        .get(4)
-                        .and_then(match_to_str)
-                        .and_then(none_if_empty)
-                        .map(Cow::Borrowed)
-                };
+        Some(OS {
+            family,
+            major: template::field(self.os_v1_replacement.as_ref(), &captures, 2),
+            minor: template::field(self.os_v2_replacement.as_ref(), &captures, 3),
+            patch: template::field(self.os_v3_replacement.as_ref(), &captures, 4),
+            patch_minor: template::field(self.os_v4_replacement.as_ref(), &captures, 5),
+        })
+    }
+}
 
-            let patch_minor: Option<Cow<'a, str>> = captures
-                .get(5)
-                .and_then(match_to_str)
-                .and_then(none_if_empty)
-                .map(Cow::Borrowed);
+impl Pattern for Matcher {
+    fn regex(&self) -> &LazyRegex {
+        &self.regex
+    }
+}
 
-            Some(OS {
-                family,
-                major,
-                minor,
-                patch,
-                patch_minor,
-            })
-        } else {
-            None
-        }
+impl Explain<'_> for Matcher {
+    fn replacements(&self, item: &OS<'_>) -> Vec<Replacement> {
+        vec![
+            replacement("family", self.os_replacement.as_ref(), Some(&item.family)),
+            replacement(
+                "major",
+                self.os_v1_replacement.as_ref(),
+                item.major.as_deref(),
+            ),
+            replacement(
+                "minor",
+                self.os_v2_replacement.as_ref(),
+                item.minor.as_deref(),
+            ),
+            replacement(
+                "patch",
+                self.os_v3_replacement.as_ref(),
+                item.patch.as_deref(),
+            ),
+            replacement(
+                "patch_minor",
+                self.os_v4_replacement.as_ref(),
+                item.patch_minor.as_deref(),
+            ),
+        ]
+        .into_iter()
+        .flatten()
+        .collect()
     }
 }
 
 impl Matcher {
-    pub fn try_from(entry: OSParserEntry, unicode: bool) -> Result<Matcher, Error> {
-        let regex = regex::bytes::RegexBuilder::new(&clean_escapes(&entry.regex))
-            .unicode(unicode)
-            .build();
+    pub fn try_from(
+        entry: OSParserEntry,
+        unicode: bool,
+        lazy: bool,
+    ) -> Result<Matcher, Error> {
+        let regex = LazyRegex::new(
+            clean_escapes(&entry.regex).into_owned(),
+            unicode,
+            None,
+            lazy,
+        )?;
+
+        Ok(Matcher {
+            regex,
+            os_replacement: entry.os_replacement.map(Template::new),
+            os_v1_replacement: entry.os_v1_replacement.map(Template::new),
+            os_v2_replacement: entry.os_v2_replacement.map(Template::new),
+            os_v3_replacement: entry.os_v3_replacement.map(Template::new),
+            os_v4_replacement: entry.os_v4_replacement.map(Template::new),
+        })
+    }
 
+    pub fn from_precompiled(
+        entry: &PrecompiledOSEntry,
+        unicode: bool,
+        lazy: bool,
+    ) -> Result<Matcher, Error> {
         Ok(Matcher {
-            regex: regex?,
-            os_replacement_has_group: entry
-                .os_replacement
-                .as_ref()
-                .map_or(false, |x| has_group(x.as_str())),
-            os_replacement: entry.os_replacement,
-            os_v1_replacement_has_group: entry
-                .os_v1_replacement
-                .as_ref()
-                .map_or(false, |x| has_group(x.as_str())),
-            os_v1_replacement: entry.os_v1_replacement,
-            os_v2_replacement_has_group: entry
-                .os_v2_replacement
-                .as_ref()
-                .map_or(false, |x| has_group(x.as_str())),
-            os_v2_replacement: entry.os_v2_replacement,
-            os_v3_replacement_has_group: entry
-                .os_v3_replacement
-                .as_ref()
-                .map_or(false, |x| has_group(x.as_str())),
-            os_v3_replacement: entry.os_v3_replacement,
+            regex: LazyRegex::new(entry.regex.to_owned(), unicode, None, lazy)?,
+            os_replacement: entry.os_replacement.map(Template::from),
+            os_v1_replacement: entry.os_v1_replacement.map(Template::from),
+            os_v2_replacement: entry.os_v2_replacement.map(Template::from),
+            os_v3_replacement: entry.os_v3_replacement.map(Template::from),
+            os_v4_replacement: entry.os_v4_replacement.map(Template::from),
         })
     }
 }
@@ -0,0 +1,573 @@
+use std::{fmt, sync::Arc};
+
+use serde_derive::Deserialize;
+
+use super::*;
+
+/// Where the rules of an overlay end up relative to the rules already loaded.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Precedence {
+    /// The overlay's rules are tried before every rule loaded so far, so they
+    /// win whenever both would match.
+    Prepend,
+    /// The overlay's rules are only tried once every rule loaded so far has
+    /// failed to match.
+    Append,
+    /// Each of the overlay's rules replaces the loaded rule with the same
+    /// `id`, keeping its position. Rules without a matching `id` are
+    /// prepended.
+    ReplaceById,
+}
+
+/// A `RegexFile` to merge into the one a parser is built from, see
+/// `UserAgentParserBuilder::with_overlay`.
+#[derive(Clone)]
+pub(super) struct Overlay {
+    pub(super) name: String,
+    pub(super) source: Source,
+    pub(super) precedence: Precedence,
+}
+
+impl Overlay {
+    /// The rules bundled with this crate as the file `name`, which are
+    /// appended to the base file.
+    pub(super) fn bundled(name: &str, regex_file: RegexFile) -> Overlay {
+        Overlay {
+            name: name.to_owned(),
+            source: Source::RegexFile(regex_file),
+            precedence: Precedence::Append,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub(super) enum Source {
+    RegexFile(RegexFile),
+    Yaml(Vec<u8>),
+}
+
+/// The YAML of an overlay. Unlike the base file, an overlay only needs to
+/// list the sections it adds rules to.
+#[derive(Deserialize)]
+#[allow(clippy::struct_field_names)]
+struct OverlayFile {
+    #[serde(default)]
+    user_agent_parsers: Vec<UserAgentParserEntry>,
+    #[serde(default)]
+    os_parsers: Vec<OSParserEntry>,
+    #[serde(default)]
+    device_parsers: Vec<DeviceParserEntry>,
+    #[serde(default)]
+    engine_parsers: Vec<EngineParserEntry>,
+    #[serde(default)]
+    architecture_parsers: Vec<ArchitectureParserEntry>,
+    #[serde(default)]
+    bot_parsers: Vec<BotParserEntry>,
+}
+
+impl From<OverlayFile> for RegexFile {
+    fn from(file: OverlayFile) -> Self {
+        RegexFile {
+            user_agent_parsers: file.user_agent_parsers,
+            os_parsers: file.os_parsers,
+            device_parsers: file.device_parsers,
+            engine_parsers: file.engine_parsers,
+            architecture_parsers: file.architecture_parsers,
+            bot_parsers: file.bot_parsers,
+        }
+    }
+}
+
+/// An error caused by a rule, or the YAML, of an overlay or one of the rule
+/// files bundled with this crate rather than the base `regexes.yaml`.
+#[derive(Debug)]
+pub struct OverlayError {
+    /// The name the overlay was registered with
+    pub name: String,
+    pub error: Box<Error>,
+}
+
+impl fmt::Display for OverlayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.name, self.error)
+    }
+}
+
+impl std::error::Error for OverlayError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        // Displayed along with the name, so `error` is not a source of its own.
+        self.error.source()
+    }
+}
+
+/// Where a rule of a merged `RegexFile` was defined.
+#[derive(Debug, Clone)]
+pub(super) struct Origin {
+    /// The overlay the rule came from, or `None` for the base file
+    pub(super) overlay: Option<Arc<str>>,
+    /// The position of the rule in its section of that file
+    pub(super) index: usize,
+}
+
+impl Origin {
+    /// Returns the origin of rule `index` according to `origins`, which is
+    /// empty when every rule is loaded at its index in the base file.
+    pub(super) fn of(origins: &[Origin], index: usize) -> Origin {
+        origins.get(index).cloned().unwrap_or(Origin {
+            overlay: None,
+            index,
+        })
+    }
+}
+
+/// The `Origin` of each rule of a merged `RegexFile`, by section and index.
+/// Without overlays or skipped rules these are left empty, as every rule
+/// comes from the base file at the index it is loaded at.
+#[derive(Debug, Clone, Default)]
+pub(super) struct Origins {
+    pub(super) device: Vec<Origin>,
+    pub(super) os: Vec<Origin>,
+    pub(super) user_agent: Vec<Origin>,
+    pub(super) engine: Vec<Origin>,
+    pub(super) architecture: Vec<Origin>,
+    pub(super) bot: Vec<Origin>,
+}
+
+impl Origins {
+    pub(super) fn get(&self, section: Section) -> &[Origin] {
+        match section {
+            Section::Device => &self.device,
+            Section::OS => &self.os,
+            Section::UserAgent => &self.user_agent,
+            Section::Engine => &self.engine,
+            Section::Architecture => &self.architecture,
+            Section::Bot => &self.bot,
+        }
+    }
+
+    /// Records that the `base` rules of `section` from the base file are
+    /// followed by `len` rules from the bundled file `name`.
+    pub(super) fn bundled(
+        &mut self,
+        section: Section,
+        base: usize,
+        name: &str,
+        len: usize,
+    ) {
+        let name: Arc<str> = Arc::from(name);
+        let origins = self.get_mut(section);
+        origins.extend((0..base).map(|index| Origin::of(&[], index)));
+        origins.extend((0..len).map(|index| Origin {
+            overlay: Some(Arc::clone(&name)),
+            index,
+        }));
+    }
+
+    pub(super) fn get_mut(&mut self, section: Section) -> &mut Vec<Origin> {
+        match section {
+            Section::Device => &mut self.device,
+            Section::OS => &mut self.os,
+            Section::UserAgent => &mut self.user_agent,
+            Section::Engine => &mut self.engine,
+            Section::Architecture => &mut self.architecture,
+            Section::Bot => &mut self.bot,
+        }
+    }
+}
+
+/// Wraps the `error` of rule `index` of `section` in a `RuleError`, and
+/// attributes it to the overlay the rule came from, if any.
+pub(super) fn locate(
+    origins: &[Origin],
+    section: Section,
+    index: usize,
+    pattern: &str,
+    error: impl Into<Error>,
+) -> Error {
+    let Origin { overlay, index } = Origin::of(origins, index);
+    let error = Error::Rule(RuleError {
+        section,
+        index,
+        pattern: pattern.to_owned(),
+        location: None,
+        error: Box::new(error.into()),
+    });
+
+    match overlay {
+        Some(name) => Error::Overlay(OverlayError {
+            name: name.to_string(),
+            error: Box::new(error),
+        }),
+        None => error,
+    }
+}
+
+/// Fills in the `location` of a `RuleError` from the YAML of the file the
+/// rule came from: `base` for the base file, or that of the overlay it names.
+pub(super) fn find_location(
+    error: &mut Error,
+    base: Option<&[u8]>,
+    overlays: &[Overlay],
+) {
+    let (rule, yaml) = match error {
+        Error::Rule(rule) => (rule, base),
+        Error::Overlay(OverlayError { name, error: inner }) => match &mut **inner {
+            Error::Rule(rule) => {
+                let yaml =
+                    overlays.iter().find(|o| o.name == *name).and_then(|o| {
+                        match &o.source {
+                            Source::Yaml(bytes) => Some(bytes.as_slice()),
+                            Source::RegexFile(_) => None,
+                        }
+                    });
+                (rule, yaml)
+            }
+            _ => return,
+        },
+        _ => return,
+    };
+
+    if let Some(yaml) = yaml {
+        rule.location = rule_error::find(yaml, rule.section, rule.index);
+    }
+}
+
+/// The parts of a rule that matter before it is compiled.
+pub(super) trait Rule {
+    fn rule_id(&self) -> Option<&str>;
+    fn pattern(&self) -> &str;
+}
+
+impl<R: Rule> Rule for &R {
+    fn rule_id(&self) -> Option<&str> {
+        (**self).rule_id()
+    }
+
+    fn pattern(&self) -> &str {
+        (**self).pattern()
+    }
+}
+
+impl Rule for DeviceParserEntry {
+    fn rule_id(&self) -> Option<&str> {
+        self.id.as_deref()
+    }
+
+    fn pattern(&self) -> &str {
+        &self.regex
+    }
+}
+
+impl Rule for OSParserEntry {
+    fn rule_id(&self) -> Option<&str> {
+        self.id.as_deref()
+    }
+
+    fn pattern(&self) -> &str {
+        &self.regex
+    }
+}
+
+impl Rule for UserAgentParserEntry {
+    fn rule_id(&self) -> Option<&str> {
+        self.id.as_deref()
+    }
+
+    fn pattern(&self) -> &str {
+        &self.regex
+    }
+}
+
+impl Rule for EngineParserEntry {
+    fn rule_id(&self) -> Option<&str> {
+        self.id.as_deref()
+    }
+
+    fn pattern(&self) -> &str {
+        &self.regex
+    }
+}
+
+impl Rule for ArchitectureParserEntry {
+    fn rule_id(&self) -> Option<&str> {
+        self.id.as_deref()
+    }
+
+    fn pattern(&self) -> &str {
+        &self.regex
+    }
+}
+
+impl Rule for BotParserEntry {
+    fn rule_id(&self) -> Option<&str> {
+        self.id.as_deref()
+    }
+
+    fn pattern(&self) -> &str {
+        &self.regex
+    }
+}
+
+/// Merges every overlay into `regex_file` in the order they were added.
+pub(super) fn apply(
+    regex_file: RegexFile,
+    overlays: &[&Overlay],
+) -> Result<(RegexFile, Origins), Error> {
+    if overlays.is_empty() {
+        return Ok((regex_file, Origins::default()));
+    }
+
+    let mut device = without_origin(regex_file.device_parsers);
+    let mut os = without_origin(regex_file.os_parsers);
+    let mut user_agent = without_origin(regex_file.user_agent_parsers);
+    let mut engine = without_origin(regex_file.engine_parsers);
+    let mut architecture = without_origin(regex_file.architecture_parsers);
+    let mut bot = without_origin(regex_file.bot_parsers);
+
+    for overlay in overlays {
+        let overlay_file = match &overlay.source {
+            Source::RegexFile(regex_file) => Cow::Borrowed(regex_file),
+            Source::Yaml(bytes) => serde_yaml::from_slice::<OverlayFile>(bytes)
+                .map(|file| Cow::Owned(file.into()))
+                .map_err(|e| {
+                    Error::Overlay(OverlayError {
+                        name: overlay.name.clone(),
+                        error: Box::new(Error::Yaml(e)),
+                    })
+                })?,
+        };
+        let name: Arc<str> = Arc::from(overlay.name.as_str());
+
+        merge(
+            &mut device,
+            &overlay_file.device_parsers,
+            &name,
+            overlay.precedence,
+        );
+        merge(&mut os, &overlay_file.os_parsers, &name, overlay.precedence);
+        merge(
+            &mut user_agent,
+            &overlay_file.user_agent_parsers,
+            &name,
+            overlay.precedence,
+        );
+        merge(
+            &mut engine,
+            &overlay_file.engine_parsers,
+            &name,
+            overlay.precedence,
+        );
+        merge(
+            &mut architecture,
+            &overlay_file.architecture_parsers,
+            &name,
+            overlay.precedence,
+        );
+        merge(
+            &mut bot,
+            &overlay_file.bot_parsers,
+            &name,
+            overlay.precedence,
+        );
+    }
+
+    let (device_parsers, device) = device.into_iter().unzip();
+    let (os_parsers, os) = os.into_iter().unzip();
+    let (user_agent_parsers, user_agent) = user_agent.into_iter().unzip();
+    let (engine_parsers, engine) = engine.into_iter().unzip();
+    let (architecture_parsers, architecture) = architecture.into_iter().unzip();
+    let (bot_parsers, bot) = bot.into_iter().unzip();
+
+    Ok((
+        RegexFile {
+            user_agent_parsers,
+            os_parsers,
+            device_parsers,
+            engine_parsers,
+            architecture_parsers,
+            bot_parsers,
+        },
+        Origins {
+            device,
+            os,
+            user_agent,
+            engine,
+            architecture,
+            bot,
+        },
+    ))
+}
+
+fn without_origin<E>(entries: Vec<E>) -> Vec<(E, Origin)> {
+    entries
+        .into_iter()
+        .enumerate()
+        .map(|(index, entry)| (entry, Origin::of(&[], index)))
+        .collect()
+}
+
+fn merge<E: Rule + Clone>(
+    rules: &mut Vec<(E, Origin)>,
+    overlay: &[E],
+    name: &Arc<str>,
+    precedence: Precedence,
+) {
+    let tagged = |(index, entry): (usize, &E)| {
+        let origin = Origin {
+            overlay: Some(Arc::clone(name)),
+            index,
+        };
+        (entry.clone(), origin)
+    };
+
+    match precedence {
+        Precedence::Prepend => {
+            rules.splice(0..0, overlay.iter().enumerate().map(tagged));
+        }
+        Precedence::Append => rules.extend(overlay.iter().enumerate().map(tagged)),
+        Precedence::ReplaceById => {
+            let mut unmatched = Vec::new();
+            for (index, entry) in overlay.iter().enumerate() {
+                let existing = entry.rule_id().and_then(|id| {
+                    rules
+                        .iter_mut()
+                        .find(|(rule, _)| rule.rule_id() == Some(id))
+                });
+                match existing {
+                    Some(rule) => *rule = tagged((index, entry)),
+                    None => unmatched.push(tagged((index, entry))),
+                }
+            }
+            rules.splice(0..0, unmatched);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Error, Location, Parser, Precedence, Section, UserAgentParser};
+
+    const BASE: &str = r"
+user_agent_parsers:
+  - id: 'firefox'
+    regex: '(Firefox)/(\d+)\.(\d+)'
+  - regex: '(Mozilla)/(\d+)'
+os_parsers:
+  - regex: '(Linux)'
+device_parsers: []
+";
+
+    const OVERLAY: &str = r"
+user_agent_parsers:
+  - regex: '(InternalApp)/(\d+)'
+  - id: 'firefox'
+    regex: '(Firefox)/(\d+)'
+    family_replacement: 'Firefox (internal)'
+os_parsers:
+  - regex: '(Linux)'
+    os_replacement: 'Internal Linux'
+device_parsers: []
+";
+
+    const UA: &str = "Mozilla/5.0 (X11; Linux x86_64) Firefox/115.0 InternalApp/3";
+
+    fn build(precedence: Precedence) -> UserAgentParser {
+        UserAgentParser::builder()
+            .with_overlay_bytes("internal.yaml", OVERLAY.as_bytes(), precedence)
+            .build_from_bytes(BASE.as_bytes())
+            .expect("Parser creation failed")
+    }
+
+    #[test]
+    fn prepended_rules_win() {
+        let client = build(Precedence::Prepend).parse(UA);
+        assert_eq!(client.user_agent.family, "InternalApp");
+        assert_eq!(client.os.family, "Internal Linux");
+    }
+
+    #[test]
+    fn appended_rules_are_fallbacks() {
+        let parser = build(Precedence::Append);
+        let client = parser.parse(UA);
+        assert_eq!(client.user_agent.family, "Firefox");
+        assert_eq!(client.os.family, "Linux");
+
+        let client = parser.parse("InternalApp/3");
+        assert_eq!(client.user_agent.family, "InternalApp");
+    }
+
+    #[test]
+    fn rules_are_replaced_by_id() {
+        let parser = build(Precedence::ReplaceById);
+        let explanation = parser.explain("Mozilla/5.0 Firefox/115.0");
+        let rule = explanation.user_agent.expect("No user agent rule");
+        assert_eq!(rule.index, 1);
+        assert_eq!(explanation.client.user_agent.family, "Firefox (internal)");
+        assert_eq!(explanation.client.user_agent.minor, None);
+
+        // The rule without an id has been prepended, ahead of the replacement.
+        assert_eq!(parser.parse(UA).user_agent.family, "InternalApp");
+    }
+
+    #[test]
+    fn overlays_only_need_the_sections_they_touch() {
+        let overlay =
+            "os_parsers:\n  - regex: '(Linux)'\n    os_replacement: 'Internal Linux'\n";
+        let parser = UserAgentParser::builder()
+            .with_overlay_bytes("linux.yaml", overlay.as_bytes(), Precedence::Prepend)
+            .build_from_bytes(BASE.as_bytes())
+            .expect("Parser creation failed");
+
+        let client = parser.parse(UA);
+        assert_eq!(client.os.family, "Internal Linux");
+        assert_eq!(client.user_agent.family, "Firefox");
+
+        // The base file still has to have every section.
+        assert!(matches!(
+            UserAgentParser::from_bytes(overlay.as_bytes()),
+            Err(Error::Yaml(_))
+        ));
+    }
+
+    #[test]
+    fn errors_name_the_overlay() {
+        let broken = "user_agent_parsers:\n  - regex: '(?<=a)b'\nos_parsers: []\ndevice_parsers: []\n";
+        let result = UserAgentParser::builder()
+            .with_overlay_bytes("broken.yaml", broken.as_bytes(), Precedence::Append)
+            .build_from_bytes(BASE.as_bytes());
+        match result {
+            Err(Error::Overlay(e)) => {
+                assert_eq!(e.name, "broken.yaml");
+                match *e.error {
+                    Error::Rule(e) => {
+                        assert_eq!(e.section, Section::UserAgent);
+                        assert_eq!(e.index, 0);
+                        assert_eq!(e.location, Some(Location { line: 2, column: 5 }));
+                    }
+                    other => panic!("Unexpected error: {:?}", other),
+                }
+            }
+            other => panic!("Unexpected result: {:?}", other),
+        }
+
+        let parser = UserAgentParser::builder()
+            .with_lazy_compilation(true)
+            .with_overlay_bytes("broken.yaml", broken.as_bytes(), Precedence::Prepend)
+            .build_from_bytes(BASE.as_bytes())
+            .expect("Parser creation failed");
+        assert!(
+            matches!(parser.validate(), Err(Error::Overlay(e)) if e.name == "broken.yaml")
+        );
+
+        let result = UserAgentParser::builder()
+            .with_overlay_bytes(
+                "invalid.yaml",
+                b"user_agent_parsers: 3",
+                Precedence::Append,
+            )
+            .build_from_bytes(BASE.as_bytes());
+        assert!(
+            matches!(result, Err(Error::Overlay(e)) if matches!(*e.error, Error::Yaml(_)))
+        );
+    }
+}
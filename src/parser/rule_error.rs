@@ -0,0 +1,316 @@
+use std::fmt;
+
+use serde::de::{self, DeserializeSeed, IgnoredAny, MapAccess, SeqAccess, Visitor};
+
+use super::Error;
+
+/// A list of rules in a regexes file.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Section {
+    UserAgent,
+    OS,
+    Device,
+    Engine,
+    Architecture,
+    Bot,
+}
+
+impl Section {
+    /// The key of the section in a regexes file, such as `os_parsers`.
+    #[must_use]
+    pub fn key(self) -> &'static str {
+        match self {
+            Section::UserAgent => "user_agent_parsers",
+            Section::OS => "os_parsers",
+            Section::Device => "device_parsers",
+            Section::Engine => "engine_parsers",
+            Section::Architecture => "architecture_parsers",
+            Section::Bot => "bot_parsers",
+        }
+    }
+}
+
+impl fmt::Display for Section {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.key())
+    }
+}
+
+/// A position in a YAML file, counting lines and columns from one.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Location {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}, column {}", self.line, self.column)
+    }
+}
+
+/// A rule that failed to load, along with where it was defined.
+#[derive(Debug)]
+pub struct RuleError {
+    pub section: Section,
+    /// The position of the rule in its section of the file that defined it,
+    /// counting from zero
+    pub index: usize,
+    /// The rule's regex as written in the file
+    pub pattern: String,
+    /// Where the rule starts, if it was loaded from YAML
+    pub location: Option<Location>,
+    pub error: Box<Error>,
+}
+
+impl fmt::Display for RuleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}[{}]", self.section, self.index)?;
+        if let Some(location) = self.location {
+            write!(f, " at {location}")?;
+        }
+        write!(f, ": {}", self.error)
+    }
+}
+
+impl std::error::Error for RuleError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        // The message already includes `error`, so it is skipped in the chain.
+        self.error.source()
+    }
+}
+
+/// Finds where rule `index` of `section` starts in `yaml`.
+///
+/// `serde_yaml` does not report the position of values it deserialized
+/// successfully, only of errors, so this walks the file up to the rule and
+/// fails on purpose there.
+pub(super) fn find(yaml: &[u8], section: Section, index: usize) -> Option<Location> {
+    let deserializer = serde_yaml::Deserializer::from_slice(yaml);
+    let error = FileProbe { section, index }
+        .deserialize(deserializer)
+        .err()?;
+    // The message is prefixed with the path to the rule.
+    if !error.to_string().contains(FOUND) {
+        return None;
+    }
+    let location = error.location()?;
+    Some(Location {
+        line: location.line(),
+        column: location.column(),
+    })
+}
+
+/// The error `RuleProbe` fails with, to tell it apart from errors in the file.
+const FOUND: &str = "rule found";
+
+struct FileProbe {
+    section: Section,
+    index: usize,
+}
+
+impl<'de> DeserializeSeed<'de> for FileProbe {
+    type Value = ();
+
+    fn deserialize<D: de::Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> Result<(), D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de> Visitor<'de> for FileProbe {
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a regexes file")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<(), A::Error> {
+        while let Some(key) = map.next_key::<String>()? {
+            if key == self.section.key() {
+                return map.next_value_seed(SectionProbe { index: self.index });
+            }
+            map.next_value::<IgnoredAny>()?;
+        }
+        Ok(())
+    }
+}
+
+struct SectionProbe {
+    index: usize,
+}
+
+impl<'de> DeserializeSeed<'de> for SectionProbe {
+    type Value = ();
+
+    fn deserialize<D: de::Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> Result<(), D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de> Visitor<'de> for SectionProbe {
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a list of rules")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+        for _ in 0..self.index {
+            if seq.next_element::<IgnoredAny>()?.is_none() {
+                return Ok(());
+            }
+        }
+        seq.next_element_seed(RuleProbe)?;
+        Ok(())
+    }
+}
+
+struct RuleProbe;
+
+impl<'de> DeserializeSeed<'de> for RuleProbe {
+    type Value = ();
+
+    fn deserialize<D: de::Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> Result<(), D::Error> {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl<'de> Visitor<'de> for RuleProbe {
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a rule")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, _: A) -> Result<(), A::Error> {
+        Err(de::Error::custom(FOUND))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Parser, Precedence, UserAgentParser};
+
+    const YAML: &str = r"
+user_agent_parsers:
+  - regex: '(Firefox)/(\d+)'
+  - id: 'chrome'
+    regex: '(Chrome)/(\d+)'
+os_parsers: [{regex: '(Linux)'}, {regex: '(Windows)'}]
+device_parsers: []
+";
+
+    #[test]
+    fn finds_rules() {
+        let find = |section, index| find(YAML.as_bytes(), section, index);
+        assert_eq!(
+            find(Section::UserAgent, 0),
+            Some(Location { line: 3, column: 5 })
+        );
+        assert_eq!(
+            find(Section::UserAgent, 1),
+            Some(Location { line: 4, column: 5 })
+        );
+        assert_eq!(find(Section::OS, 1).map(|l| l.line), Some(6));
+        assert_eq!(find(Section::UserAgent, 2), None);
+        assert_eq!(find(Section::Device, 0), None);
+        assert_eq!(find(Section::Bot, 0), None);
+    }
+
+    #[test]
+    fn locates_rules_in_files() {
+        let path = std::env::temp_dir()
+            .join(format!("uaparser-{}-rule-error.yaml", std::process::id()));
+        let broken = YAML.replace("(Chrome)", "(?=Chrome)");
+        std::fs::write(&path, broken).unwrap();
+
+        let error = UserAgentParser::from_file(std::fs::File::open(&path).unwrap())
+            .expect_err("Invalid rule loaded");
+        std::fs::remove_file(&path).unwrap();
+        let rule = error.rule().expect("Not a rule error");
+        assert_eq!((rule.section, rule.index), (Section::UserAgent, 1));
+        assert_eq!(rule.location, Some(Location { line: 4, column: 5 }));
+    }
+
+    #[test]
+    fn error_chain_mentions_the_cause_once() {
+        let broken = YAML.replace("(Chrome)", "(?=Chrome)");
+        let overlay = YAML.replace("(Linux)", "(?=Linux)");
+        let errors = [
+            UserAgentParser::from_bytes(broken.as_bytes()),
+            UserAgentParser::builder()
+                .with_overlay_bytes(
+                    "overlay.yaml",
+                    overlay.as_bytes(),
+                    Precedence::Append,
+                )
+                .build_from_bytes(YAML.as_bytes()),
+        ];
+        for error in errors {
+            let error = error.expect_err("Invalid rule loaded");
+            let mut chain = vec![error.to_string()];
+            let mut source = std::error::Error::source(&error);
+            while let Some(e) = source {
+                chain.push(e.to_string());
+                source = e.source();
+            }
+            let report = chain.join(": ");
+            assert_eq!(report.matches("look-around").count(), 1, "{}", report);
+        }
+    }
+
+    #[test]
+    fn lenient_loading_skips_invalid_rules() {
+        let yaml = r"
+user_agent_parsers:
+  - regex: '(Firefox)/(\d+)'
+  - regex: '(?<!Mobile )(Safari)/(\d+)'
+  - regex: '(Chrome)/(\d+)'
+os_parsers: []
+device_parsers: []
+";
+        let overlay = "user_agent_parsers: []\nos_parsers:\n  - regex: '(?=Linux)(Linux)'\ndevice_parsers: []\n";
+        let builder = UserAgentParser::builder().with_overlay_bytes(
+            "overlay.yaml",
+            overlay.as_bytes(),
+            Precedence::Append,
+        );
+        assert!(builder.clone().build_from_bytes(yaml.as_bytes()).is_err());
+
+        let parser = builder
+            .with_lenient_loading(true)
+            .build_from_bytes(yaml.as_bytes())
+            .expect("Parser creation failed");
+        assert_eq!(parser.parse_user_agent("Chrome/114").family, "Chrome");
+        assert_eq!(parser.parse_user_agent("Safari/605").family, "Other");
+        assert!(parser.validate().is_ok());
+
+        assert_eq!(parser.skipped(Section::UserAgent), 1);
+        assert_eq!(parser.skipped(Section::OS), 1);
+        assert_eq!(parser.skipped(Section::Device), 0);
+
+        let rule = parser.skipped_rules()[1].rule().expect("Not a rule error");
+        assert_eq!(rule.index, 1);
+        assert_eq!(rule.location.map(|l| l.line), Some(4));
+        match &parser.skipped_rules()[0] {
+            Error::Overlay(e) => {
+                assert_eq!(e.name, "overlay.yaml");
+                assert_eq!(
+                    e.error.rule().map(|rule| rule.location),
+                    Some(Some(Location { line: 3, column: 5 }))
+                );
+            }
+            other => panic!("Unexpected error: {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,256 @@
+use std::borrow::Cow;
+
+use regex::bytes::Captures;
+
+use super::{match_to_str, none_if_empty};
+
+/// A replacement such as `Samsung $1`, parsed into literal text and capture
+/// group references when the rule is loaded.
+///
+/// `$0` to `$9` and `${n}` refer to groups by number, `${name}` to a named
+/// group, and `$$` stands for a literal `$`. Any other `$` is kept as is.
+#[derive(Debug, Clone)]
+pub(super) struct Template {
+    source: String,
+    segments: Vec<Segment>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    /// Literal text, which borrows from precompiled regexes where it can
+    Literal(Cow<'static, str>),
+    Group(usize),
+    Named(String),
+}
+
+impl Template {
+    pub(super) fn new(source: String) -> Template {
+        let mut segments = Vec::new();
+        let mut literal = String::new();
+        let mut rest = source.as_str();
+        while let Some(at) = rest.find('$') {
+            literal.push_str(&rest[..at]);
+            let after = &rest[at + 1..];
+            let Some((segment, len)) = reference(after) else {
+                // `$$` is an escaped `$`, and a lone `$` is kept as written.
+                literal.push('$');
+                rest = after.strip_prefix('$').unwrap_or(after);
+                continue;
+            };
+            if !literal.is_empty() {
+                let literal = std::mem::take(&mut literal);
+                segments.push(Segment::Literal(Cow::Owned(literal)));
+            }
+            segments.push(segment);
+            rest = &after[len..];
+        }
+        literal.push_str(rest);
+        if segments.is_empty() {
+            // Without any groups the result is the same every time, so it is
+            // trimmed once here rather than on every match.
+            trim(&mut literal);
+        }
+        if !literal.is_empty() {
+            segments.push(Segment::Literal(Cow::Owned(literal)));
+        }
+
+        Template { source, segments }
+    }
+
+    pub(super) fn as_str(&self) -> &str {
+        &self.source
+    }
+
+    /// Fills in the groups of the template from `captures`. Groups that did
+    /// not take part in the match, or that the regex does not have, expand to
+    /// nothing, and the result is trimmed, as in the other uap
+    /// implementations. A template that is a single group borrows from the
+    /// matched text, and one without groups from the precompiled regexes it
+    /// came from, instead of allocating.
+    pub(super) fn expand<'a>(&self, captures: &Captures<'a>) -> Cow<'a, str> {
+        match self.segments.as_slice() {
+            [] => Cow::Borrowed(""),
+            [Segment::Literal(literal)] => literal.clone(),
+            [segment] => Cow::Borrowed(group(segment, captures).unwrap_or("").trim()),
+            segments => {
+                let mut expanded = String::with_capacity(self.source.len());
+                for segment in segments {
+                    match segment {
+                        Segment::Literal(literal) => expanded.push_str(literal),
+                        _ => expanded.push_str(group(segment, captures).unwrap_or("")),
+                    }
+                }
+                trim(&mut expanded);
+                Cow::Owned(expanded)
+            }
+        }
+    }
+}
+
+/// Parses the group reference at the start of `text`, which follows a `$`,
+/// returning it along with its length.
+fn reference(text: &str) -> Option<(Segment, usize)> {
+    if let Some(digit) = text.chars().next().and_then(|c| c.to_digit(10)) {
+        return Some((Segment::Group(digit as usize), 1));
+    }
+
+    let name = text.strip_prefix('{')?;
+    let name = &name[..name.find('}')?];
+    if name.is_empty() || !name.chars().all(|c| c.is_alphanumeric() || c == '_') {
+        return None;
+    }
+    let segment = match name.parse() {
+        Ok(group) => Segment::Group(group),
+        Err(_) => Segment::Named(name.to_owned()),
+    };
+    Some((segment, name.len() + 2))
+}
+
+/// Returns the text a group reference matched, if any.
+fn group<'a>(segment: &Segment, captures: &Captures<'a>) -> Option<&'a str> {
+    let m = match segment {
+        Segment::Group(group) => captures.get(*group),
+        Segment::Named(name) => captures.name(name),
+        Segment::Literal(_) => None,
+    };
+    m.and_then(match_to_str)
+}
+
+/// Trims whitespace from both ends of `text` without reallocating.
+fn trim(text: &mut String) {
+    text.truncate(text.trim_end().len());
+    let start = text.len() - text.trim_start().len();
+    text.drain(..start);
+}
+
+impl From<&'static str> for Template {
+    fn from(source: &'static str) -> Self {
+        let mut template = Template::new(source.to_owned());
+        if let [Segment::Literal(literal)] = template.segments.as_mut_slice() {
+            // Unless an escape changed the text, it can borrow from `source`.
+            if literal == source.trim() {
+                *literal = Cow::Borrowed(source.trim());
+            }
+        }
+        template
+    }
+}
+
+/// Returns a field of a parsed item: `template` expanded if the rule defines
+/// one, or capture group `group` otherwise. Empty values count as missing.
+pub(super) fn field<'a>(
+    template: Option<&Template>,
+    captures: &Captures<'a>,
+    group: usize,
+) -> Option<Cow<'a, str>> {
+    match template {
+        Some(template) => none_if_empty(template.expand(captures)),
+        None => capture(captures, group),
+    }
+}
+
+/// Returns capture group `group`, unless it is missing or empty.
+pub(super) fn capture<'a>(captures: &Captures<'a>, group: usize) -> Option<Cow<'a, str>> {
+    captures
+        .get(group)
+        .and_then(match_to_str)
+        .and_then(none_if_empty)
+        .map(Cow::Borrowed)
+}
+
+#[cfg(test)]
+mod tests {
+    use regex::bytes::Regex;
+
+    use super::*;
+    use crate::{OSRule, Parser, RegexFile, UserAgentParser, UserAgentRule};
+
+    #[test]
+    fn expands_groups() {
+        let regex = Regex::new(r"(Foo)/(\d+)(?:\.(\d+))?").unwrap();
+        let captures = regex.captures(b"Foo/3").unwrap();
+        let expand = |source: &str| Template::new(source.to_owned()).expand(&captures);
+
+        assert_eq!(expand("$1 Mobile"), "Foo Mobile");
+        assert_eq!(expand("v$2"), "v3");
+        assert_eq!(expand("$2.$3"), "3.");
+        assert_eq!(expand(" $3 $1 "), "Foo");
+        assert_eq!(expand("$0"), "Foo/3");
+        assert_eq!(expand("$1$2"), "Foo3");
+        assert_eq!(expand("US$"), "US$");
+        assert_eq!(expand(" Literal "), "Literal");
+        assert_eq!(Template::new("$1 Mobile".to_owned()).as_str(), "$1 Mobile");
+    }
+
+    #[test]
+    fn handles_escapes_and_named_groups() {
+        let regex = Regex::new(r"(?P<name>Foo)/(?P<major>\d+)").unwrap();
+        let captures = regex.captures(b"Foo/3").unwrap();
+        let expand = |source: &str| Template::new(source.to_owned()).expand(&captures);
+
+        assert_eq!(expand("$$1"), "$1");
+        assert_eq!(expand("$$$1"), "$Foo");
+        assert_eq!(expand("${name} ${major}"), "Foo 3");
+        assert_eq!(expand("${1}0"), "Foo0");
+        assert_eq!(expand("${2}.${missing}"), "3.");
+        assert_eq!(expand("$7 $1"), "Foo");
+        assert_eq!(expand("${12}"), "");
+        assert_eq!(expand("${} ${a-b} ${1"), "${} ${a-b} ${1");
+    }
+
+    #[test]
+    fn single_groups_borrow() {
+        let regex = Regex::new(r"(Foo)/( \d+ )").unwrap();
+        let captures = regex.captures(b"Foo/ 3 ").unwrap();
+        let expand = |source: &str| Template::new(source.to_owned()).expand(&captures);
+
+        assert!(matches!(expand("$2"), Cow::Borrowed("3")));
+        assert!(matches!(expand("${1}"), Cow::Borrowed("Foo")));
+        assert!(matches!(expand("$5"), Cow::Borrowed("")));
+        assert!(matches!(expand("$1 $2"), Cow::Owned(s) if s == "Foo  3"));
+
+        let precompiled = |source: &'static str| Template::from(source).expand(&captures);
+        assert!(matches!(precompiled(" Bar "), Cow::Borrowed("Bar")));
+        assert!(matches!(precompiled("US$$"), Cow::Owned(s) if s == "US$"));
+    }
+
+    #[test]
+    fn falls_back_to_groups() {
+        let regex = Regex::new(r"(Foo)/(\d+)()").unwrap();
+        let captures = regex.captures(b"Foo/3").unwrap();
+        let template = Template::new("$2".to_owned());
+
+        assert_eq!(field(Some(&template), &captures, 1).as_deref(), Some("3"));
+        assert_eq!(field(None, &captures, 1).as_deref(), Some("Foo"));
+        assert_eq!(field(None, &captures, 3), None);
+        assert_eq!(field(None, &captures, 4), None);
+    }
+
+    #[test]
+    fn every_field_expands_groups() {
+        let regex_file = RegexFile::default()
+            .user_agent(
+                UserAgentRule::new(r"(Foo)Browser/(\d+)_(\d+)")
+                    .family("$1")
+                    .v1("$2")
+                    .v2("$3")
+                    .v3("b$3"),
+            )
+            .os(OSRule::new(r"(FooOS) (\d+)\.(\d+)\.(\d+)\.(\d+)")
+                .family("$1 Mobile")
+                .v1("$2")
+                .v4("p$5"));
+        let parser =
+            UserAgentParser::try_from(regex_file).expect("Parser creation failed");
+
+        let client = parser.parse("FooBrowser/12_3 (FooOS 1.2.3.4)");
+        assert_eq!(client.user_agent.family, "Foo");
+        assert_eq!(client.user_agent.major.as_deref(), Some("12"));
+        assert_eq!(client.user_agent.minor.as_deref(), Some("3"));
+        assert_eq!(client.user_agent.patch.as_deref(), Some("b3"));
+        assert_eq!(client.os.family, "FooOS Mobile");
+        assert_eq!(client.os.major.as_deref(), Some("1"));
+        assert_eq!(client.os.minor.as_deref(), Some("2"));
+        assert_eq!(client.os.patch_minor.as_deref(), Some("p4"));
+    }
+}
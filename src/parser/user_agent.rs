@@ -5,81 +5,64 @@ pub enum Error {
     Regex(regex::Error),
 }
 
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Regex(e) => e.source(),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Matcher {
-    regex: regex::bytes::Regex,
-    family_replacement_has_group: bool,
-    family_replacement: Option<String>,
-    v1_replacement: Option<String>,
-    v2_replacement: Option<String>,
-    v3_replacement: Option<String>,
+    regex: LazyRegex,
+    family_replacement: Option<Template>,
+    v1_replacement: Option<Template>,
+    v2_replacement: Option<Template>,
+    v3_replacement: Option<Template>,
 }
 
 impl<'a> SubParser<'a> for Matcher {
     type Item = UserAgent<'a>;
 
     fn try_parse(&self, text: &'a str) -> Option<Self::Item> {
-        if let Some(captures) = self.regex.captures(text.as_bytes()) {
-            let family: Cow<'a, str> =
-                if let Some(family_replacement) = &self.family_replacement {
-                    replace_cow(
-                        family_replacement,
-                        self.family_replacement_has_group,
-                        &captures,
-                    )
-                } else {
-                    captures
-                        .get(1)
-                        .and_then(match_to_str)
-                        .and_then(none_if_empty)
-                        .map(Cow::Borrowed)?
-                };
+        let captures = self.regex.get()?.captures(text.as_bytes())?;
 
-            let major: Option<Cow<'a, str>> = self
-                .v1_replacement
-                .as_ref()
-                .map(|x| Cow::Owned(x.clone()))
-                .or_else(|| {
-                    captures
-                        .get(2)
-                        .and_then(match_to_str)
-                        .and_then(none_if_empty)
-                        .map(Cow::Borrowed)
-                });
+        let family: Cow<'a, str> = match &self.family_replacement {
+            Some(family_replacement) => family_replacement.expand(&captures),
+            None => template::capture(&captures, 1)?,
+        };
 
-            let minor: Option<Cow<'a, str>> = self
-                .v2_replacement
-                .as_ref()
-                .map(|x| Cow::Owned(x.clone()))
-                .or_else(|| {
-                    captures
-                        .get(3)
-                        .and_then(match_to_str)
-                        .and_then(none_if_empty)
-                        .map(Cow::Borrowed)
-                });
+        Some(UserAgent {
+            family,
+            major: template::field(self.v1_replacement.as_ref(), &captures, 2),
+            minor: template::field(self.v2_replacement.as_ref(), &captures, 3),
+            patch: template::field(self.v3_replacement.as_ref(), &captures, 4),
+        })
+    }
+}
 
-            let patch: Option<Cow<'a, str>> = self
-                .v3_replacement
-                .as_ref()
-                .map(|x| Cow::Owned(x.clone()))
-                .or_else(|| {
-                    captures
-                        .get(4)
-                        .and_then(match_to_str)
-                        .and_then(none_if_empty)
-                        .map(Cow::Borrowed)
-                });
+impl Pattern for Matcher {
+    fn regex(&self) -> &LazyRegex {
+        &self.regex
+    }
+}
 
-            Some(UserAgent {
-                family,
-                major,
-                minor,
-                patch,
-            })
-        } else {
-            None
-        }
+impl Explain<'_> for Matcher {
+    fn replacements(&self, item: &UserAgent<'_>) -> Vec<Replacement> {
+        vec![
+            replacement(
+                "family",
+                self.family_replacement.as_ref(),
+                Some(&item.family),
+            ),
+            replacement("major", self.v1_replacement.as_ref(), item.major.as_deref()),
+            replacement("minor", self.v2_replacement.as_ref(), item.minor.as_deref()),
+            replacement("patch", self.v3_replacement.as_ref(), item.patch.as_deref()),
+        ]
+        .into_iter()
+        .flatten()
+        .collect()
     }
 }
 
@@ -87,22 +70,40 @@ impl Matcher {
     pub fn try_from(
         entry: UserAgentParserEntry,
         unicode: bool,
+        lazy: bool,
     ) -> Result<Matcher, Error> {
-        let regex = regex::bytes::RegexBuilder::new(&clean_escapes(&entry.regex))
-            .unicode(unicode)
-            .size_limit(20 * (1 << 20))
-            .build();
+        let regex = LazyRegex::new(
+            clean_escapes(&entry.regex).into_owned(),
+            unicode,
+            Some(20 * (1 << 20)),
+            lazy,
+        )?;
+
+        Ok(Matcher {
+            regex,
+            family_replacement: entry.family_replacement.map(Template::new),
+            v1_replacement: entry.v1_replacement.map(Template::new),
+            v2_replacement: entry.v2_replacement.map(Template::new),
+            v3_replacement: entry.v3_replacement.map(Template::new),
+        })
+    }
 
+    pub fn from_precompiled(
+        entry: &PrecompiledUserAgentEntry,
+        unicode: bool,
+        lazy: bool,
+    ) -> Result<Matcher, Error> {
         Ok(Matcher {
-            regex: regex?,
-            family_replacement_has_group: entry
-                .family_replacement
-                .as_ref()
-                .map_or(false, |x| has_group(x.as_str())),
-            family_replacement: entry.family_replacement,
-            v1_replacement: entry.v1_replacement,
-            v2_replacement: entry.v2_replacement,
-            v3_replacement: entry.v3_replacement,
+            regex: LazyRegex::new(
+                entry.regex.to_owned(),
+                unicode,
+                Some(20 * (1 << 20)),
+                lazy,
+            )?,
+            family_replacement: entry.family_replacement.map(Template::from),
+            v1_replacement: entry.v1_replacement.map(Template::from),
+            v2_replacement: entry.v2_replacement.map(Template::from),
+            v3_replacement: entry.v3_replacement.map(Template::from),
         })
     }
 }
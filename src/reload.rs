@@ -0,0 +1,365 @@
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, PoisonError, RwLock, Weak,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, SystemTime},
+};
+
+use super::{
+    Architecture, BotInfo, Client, Device, Engine, Error, Parser, UserAgent,
+    UserAgentParser, UserAgentParserBuilder, OS,
+};
+
+type ReloadCallback = Box<dyn Fn(&UserAgentParser) + Send + Sync>;
+type ErrorCallback = Box<dyn Fn(&Error) + Send + Sync>;
+
+/// A `Parser` whose `UserAgentParser` can be swapped out while it is in use,
+/// so a long-running service can pick up a new `regexes.yaml` without a
+/// restart.
+///
+/// Every parse uses the parser that was current when it started. A reload
+/// builds and validates the new parser off to the side and only swaps it in
+/// if that succeeds, so a broken file leaves the old parser serving.
+///
+/// ```rust,no_run
+/// # use std::{sync::Arc, time::Duration};
+/// # use uaparser::*;
+/// let parser = ReloadableParser::from_yaml("/etc/uaparser/regexes.yaml")?
+///     .on_reload(|_| eprintln!("regexes.yaml reloaded"))
+///     .on_error(|e| eprintln!("keeping the old regexes.yaml: {e}"));
+/// let parser = Arc::new(parser);
+/// let _watcher = parser.watch(Duration::from_secs(30));
+///
+/// let client = parser.parse("Mozilla/5.0 (X11; Linux x86_64) Firefox/115.0");
+/// # Ok::<(), Error>(())
+/// ```
+pub struct ReloadableParser {
+    current: RwLock<Arc<UserAgentParser>>,
+    path: PathBuf,
+    builder: UserAgentParserBuilder,
+    on_reload: Vec<ReloadCallback>,
+    on_error: Vec<ErrorCallback>,
+}
+
+impl ReloadableParser {
+    /// Builds a parser from the `regexes.yaml` at `path`, which `reload`
+    /// reads again.
+    pub fn from_yaml(path: impl Into<PathBuf>) -> Result<Self, Error> {
+        Self::with_builder(path, UserAgentParser::builder())
+    }
+
+    /// Like `from_yaml`, but builds the parser, and every reloaded one, with
+    /// the options and overlays of `builder`.
+    pub fn with_builder(
+        path: impl Into<PathBuf>,
+        builder: UserAgentParserBuilder,
+    ) -> Result<Self, Error> {
+        let path = path.into();
+        let parser = build(&path, builder.clone())?;
+        Ok(ReloadableParser {
+            current: RwLock::new(Arc::new(parser)),
+            path,
+            builder,
+            on_reload: Vec::new(),
+            on_error: Vec::new(),
+        })
+    }
+
+    /// Registers a callback to run with the new parser after every
+    /// successful reload.
+    #[must_use]
+    pub fn on_reload(
+        mut self,
+        callback: impl Fn(&UserAgentParser) + Send + Sync + 'static,
+    ) -> Self {
+        self.on_reload.push(Box::new(callback));
+        self
+    }
+
+    /// Registers a callback to run with the error of every failed reload.
+    #[must_use]
+    pub fn on_error(mut self, callback: impl Fn(&Error) + Send + Sync + 'static) -> Self {
+        self.on_error.push(Box::new(callback));
+        self
+    }
+
+    /// The file the parser is reloaded from.
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the parser currently in use. Holding on to it does not keep a
+    /// reload from swapping in a new one.
+    #[must_use]
+    pub fn current(&self) -> Arc<UserAgentParser> {
+        Arc::clone(&self.current.read().unwrap_or_else(PoisonError::into_inner))
+    }
+
+    /// Rebuilds the parser from `path` and swaps it in. If the file cannot be
+    /// read, or any of its rules fail to compile, the current parser is kept
+    /// and the error is passed to the `on_error` callbacks and returned.
+    pub fn reload(&self) -> Result<(), Error> {
+        match build(&self.path, self.builder.clone()) {
+            Ok(parser) => {
+                self.replace(parser);
+                Ok(())
+            }
+            Err(e) => {
+                for callback in &self.on_error {
+                    callback(&e);
+                }
+                Err(e)
+            }
+        }
+    }
+
+    /// Swaps in `parser`, running the `on_reload` callbacks.
+    pub fn replace(&self, parser: UserAgentParser) {
+        let parser = Arc::new(parser);
+        *self.current.write().unwrap_or_else(PoisonError::into_inner) =
+            Arc::clone(&parser);
+        for callback in &self.on_reload {
+            callback(&parser);
+        }
+    }
+
+    /// Starts a background thread that checks `path` every `interval` and
+    /// reloads the parser when the file's modification time or size changes.
+    /// The thread stops when the returned `Watcher` or the parser is dropped.
+    #[must_use = "dropping the Watcher stops watching"]
+    pub fn watch(self: &Arc<Self>, interval: Duration) -> Watcher {
+        // Taken here rather than on the thread, so changes made right after
+        // this returns are not mistaken for the starting point.
+        let last = stamp(&self.path);
+        let parser = Arc::downgrade(self);
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread = {
+            let stop = Arc::clone(&stop);
+            thread::spawn(move || poll(&parser, &stop, interval, last))
+        };
+
+        Watcher {
+            stop,
+            thread: Some(thread),
+        }
+    }
+}
+
+impl fmt::Debug for ReloadableParser {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ReloadableParser")
+            .field("path", &self.path)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Parser for ReloadableParser {
+    fn parse<'a>(&self, user_agent: &'a str) -> Client<'a> {
+        self.current().parse(user_agent)
+    }
+
+    fn parse_device<'a>(&self, user_agent: &'a str) -> Device<'a> {
+        self.current().parse_device(user_agent)
+    }
+
+    fn parse_os<'a>(&self, user_agent: &'a str) -> OS<'a> {
+        self.current().parse_os(user_agent)
+    }
+
+    fn parse_user_agent<'a>(&self, user_agent: &'a str) -> UserAgent<'a> {
+        self.current().parse_user_agent(user_agent)
+    }
+
+    fn parse_engine<'a>(&self, user_agent: &'a str) -> Engine<'a> {
+        self.current().parse_engine(user_agent)
+    }
+
+    fn parse_architecture<'a>(&self, user_agent: &'a str) -> Architecture<'a> {
+        self.current().parse_architecture(user_agent)
+    }
+
+    fn parse_bot<'a>(&self, user_agent: &'a str) -> Option<BotInfo<'a>> {
+        self.current().parse_bot(user_agent)
+    }
+}
+
+/// Watches the file of a `ReloadableParser`, see `ReloadableParser::watch`.
+#[derive(Debug)]
+pub struct Watcher {
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Watcher {
+    /// Stops watching, waiting for a reload in progress to finish.
+    pub fn stop(mut self) {
+        self.shutdown();
+    }
+
+    fn shutdown(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            thread.thread().unpark();
+            // A panicking callback already reported itself.
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for Watcher {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+/// Builds the parser and compiles every regex, so a file that only fails
+/// under lazy compilation is still rejected before it is swapped in.
+fn build(path: &Path, builder: UserAgentParserBuilder) -> Result<UserAgentParser, Error> {
+    let file = std::fs::File::open(path)?;
+    let parser = builder.build_from_file(file)?;
+    parser.validate()?;
+    Ok(parser)
+}
+
+/// Identifies a version of a file without reading it.
+fn stamp(path: &Path) -> Option<(SystemTime, u64)> {
+    let metadata = std::fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+fn poll(
+    parser: &Weak<ReloadableParser>,
+    stop: &AtomicBool,
+    interval: Duration,
+    mut last: Option<(SystemTime, u64)>,
+) {
+    loop {
+        thread::park_timeout(interval);
+        if stop.load(Ordering::Relaxed) {
+            return;
+        }
+        let Some(parser) = parser.upgrade() else {
+            return;
+        };
+
+        // A file that is missing for a moment, as while it is being
+        // replaced, is simply checked again next time.
+        let current = stamp(&parser.path);
+        if current.is_some() && current != last {
+            last = current;
+            // Errors were already passed to the `on_error` callbacks.
+            let _ = parser.reload();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicUsize;
+
+    use super::*;
+    use crate::{RuleError, Section};
+
+    const FIREFOX: &str = r"
+user_agent_parsers:
+  - regex: '(Firefox)/(\d+)\.(\d+)'
+os_parsers: []
+device_parsers: []
+";
+
+    const CHROME: &str = r"
+user_agent_parsers:
+  - regex: '(Chrome)/(\d+)\.(\d+)'
+os_parsers: []
+device_parsers: []
+";
+
+    const BROKEN: &str = r"
+user_agent_parsers:
+  - regex: '(Chrome'
+os_parsers: []
+device_parsers: []
+";
+
+    const USER_AGENT: &str = "Mozilla/5.0 Chrome/114.0 Firefox/115.0";
+
+    fn regexes_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("uaparser-{}-{name}.yaml", std::process::id()))
+    }
+
+    #[test]
+    fn keeps_serving_when_reload_fails() {
+        let path = regexes_path("reload");
+        std::fs::write(&path, FIREFOX).unwrap();
+
+        let reloads = Arc::new(AtomicUsize::new(0));
+        let errors = Arc::new(AtomicUsize::new(0));
+        let parser = {
+            let reloads = Arc::clone(&reloads);
+            let errors = Arc::clone(&errors);
+            ReloadableParser::with_builder(
+                &path,
+                UserAgentParser::builder().with_lazy_compilation(true),
+            )
+            .unwrap()
+            .on_reload(move |_| {
+                reloads.fetch_add(1, Ordering::Relaxed);
+            })
+            .on_error(move |_| {
+                errors.fetch_add(1, Ordering::Relaxed);
+            })
+        };
+        let before = parser.current();
+        assert_eq!(parser.parse_user_agent(USER_AGENT).family, "Firefox");
+
+        std::fs::write(&path, CHROME).unwrap();
+        parser.reload().unwrap();
+        assert_eq!(parser.parse_user_agent(USER_AGENT).family, "Chrome");
+        assert_eq!(before.parse_user_agent(USER_AGENT).family, "Firefox");
+
+        std::fs::write(&path, BROKEN).unwrap();
+        assert!(matches!(
+            parser.reload(),
+            Err(Error::Rule(RuleError {
+                section: Section::UserAgent,
+                ..
+            }))
+        ));
+        assert_eq!(parser.parse(USER_AGENT).user_agent.family, "Chrome");
+
+        std::fs::remove_file(&path).unwrap();
+        assert!(matches!(parser.reload(), Err(Error::IO(_))));
+        assert_eq!(reloads.load(Ordering::Relaxed), 1);
+        assert_eq!(errors.load(Ordering::Relaxed), 2);
+    }
+
+    #[test]
+    fn reloads_when_the_file_changes() {
+        let path = regexes_path("watch");
+        std::fs::write(&path, FIREFOX).unwrap();
+
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let sender = std::sync::Mutex::new(sender);
+        let parser = ReloadableParser::from_yaml(&path)
+            .unwrap()
+            .on_reload(move |_| {
+                sender.lock().unwrap().send(()).unwrap();
+            });
+        let parser = Arc::new(parser);
+        let watcher = parser.watch(Duration::from_millis(10));
+
+        // Pad the file so its size changes even if the modification time is
+        // too coarse to tell the writes apart.
+        std::fs::write(&path, format!("{CHROME}\n# reloaded\n")).unwrap();
+        receiver.recv_timeout(Duration::from_secs(10)).unwrap();
+        assert_eq!(parser.parse_user_agent(USER_AGENT).family, "Chrome");
+
+        watcher.stop();
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,333 @@
+use std::{borrow::Cow, cmp::Reverse, collections::BTreeMap, iter::FromIterator};
+
+use super::{Client, Deserialize, Serialize};
+
+/// A way of breaking down the clients counted by `UsageStats`.
+#[derive(
+    Clone, Copy, Debug, Serialize, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum Dimension {
+    /// The `UserAgent` family, such as `Firefox`
+    Browser,
+    /// The `UserAgent` family and major version, such as `Firefox 115`
+    BrowserVersion,
+    /// The `OS` family, such as `Windows`
+    Os,
+    /// The `OS` family and major version, such as `Windows 10`
+    OsVersion,
+    /// The `Device` family
+    Device,
+    /// The `Device` brand, or `Other` if there is none
+    DeviceBrand,
+    /// The `DeviceType`, such as `mobile`
+    DeviceType,
+    /// The `Engine` family, such as `Gecko`
+    Engine,
+    /// The `Architecture` family, such as `x86_64`
+    Architecture,
+    /// The `BotInfo` name. Clients that are not bots are not counted here.
+    Bot,
+}
+
+impl Dimension {
+    /// Every dimension, in the order `UsageStats::report` lists them.
+    pub const ALL: [Dimension; 10] = [
+        Dimension::Browser,
+        Dimension::BrowserVersion,
+        Dimension::Os,
+        Dimension::OsVersion,
+        Dimension::Device,
+        Dimension::DeviceBrand,
+        Dimension::DeviceType,
+        Dimension::Engine,
+        Dimension::Architecture,
+        Dimension::Bot,
+    ];
+
+    fn key<'a>(self, client: &'a Client<'_>) -> Option<Cow<'a, str>> {
+        let with_major = |family: &'a str, major: Option<&'a str>| match major {
+            Some(major) => Cow::Owned(format!("{family} {major}")),
+            None => Cow::Borrowed(family),
+        };
+
+        Some(match self {
+            Dimension::Browser => Cow::Borrowed(&client.user_agent.family),
+            Dimension::BrowserVersion => with_major(
+                &client.user_agent.family,
+                client.user_agent.major.as_deref(),
+            ),
+            Dimension::Os => Cow::Borrowed(&client.os.family),
+            Dimension::OsVersion => {
+                with_major(&client.os.family, client.os.major.as_deref())
+            }
+            Dimension::Device => Cow::Borrowed(&client.device.family),
+            Dimension::DeviceBrand => {
+                Cow::Borrowed(client.device.brand.as_deref().unwrap_or("Other"))
+            }
+            Dimension::DeviceType => Cow::Borrowed(client.device_type().as_str()),
+            Dimension::Engine => Cow::Borrowed(&client.engine.family),
+            Dimension::Architecture => Cow::Borrowed(&client.architecture.family),
+            Dimension::Bot => Cow::Borrowed(&client.bot.as_ref()?.name),
+        })
+    }
+}
+
+/// Tallies parsed `Client`s by browser, OS, device and the other
+/// `Dimension`s, so the usual breakdowns are computed the same way
+/// everywhere.
+///
+/// Each thread can fill its own `UsageStats` and `merge` them at the end.
+///
+/// ```rust
+/// # use uaparser::*;
+/// let regexes = br"
+/// user_agent_parsers:
+///   - regex: '(Firefox)/(\d+)\.(\d+)'
+/// os_parsers: []
+/// device_parsers: []
+/// ";
+/// let parser = UserAgentParser::from_bytes(regexes).unwrap();
+///
+/// let mut stats = UsageStats::default();
+/// for ua in ["Firefox/115.0", "Firefox/115.1", "Firefox/102.0", "curl/8.0"] {
+///     stats.add(&parser.parse(ua));
+/// }
+///
+/// let top = stats.top(Dimension::BrowserVersion, 2);
+/// assert_eq!((top[0].value.as_str(), top[0].count), ("Firefox 115", 2));
+/// assert_eq!(top[0].percent, 50.0);
+/// assert_eq!(stats.count(Dimension::Browser, "Firefox"), 3);
+/// ```
+#[derive(Clone, Debug, Default, Serialize, Deserialize, Eq, PartialEq)]
+pub struct UsageStats {
+    total: u64,
+    counts: BTreeMap<Dimension, BTreeMap<String, u64>>,
+}
+
+/// An entry of a `UsageStats::top` table.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct Share {
+    pub value: String,
+    pub count: u64,
+    /// `count` as a percentage of all clients counted
+    pub percent: f64,
+}
+
+/// The top entries of every `Dimension`, as returned by `UsageStats::report`.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct Report {
+    pub total: u64,
+    pub tables: BTreeMap<Dimension, Vec<Share>>,
+}
+
+impl UsageStats {
+    /// Counts a single client.
+    pub fn add(&mut self, client: &Client<'_>) {
+        self.total += 1;
+        for dimension in Dimension::ALL {
+            if let Some(key) = dimension.key(client) {
+                let counts = self.counts.entry(dimension).or_default();
+                if let Some(count) = counts.get_mut(key.as_ref()) {
+                    *count += 1;
+                } else {
+                    counts.insert(key.into_owned(), 1);
+                }
+            }
+        }
+    }
+
+    /// Adds the counts of `other`, such as those gathered by another thread.
+    pub fn merge(&mut self, other: UsageStats) {
+        self.total += other.total;
+        for (dimension, other) in other.counts {
+            let counts = self.counts.entry(dimension).or_default();
+            for (key, count) in other {
+                *counts.entry(key).or_default() += count;
+            }
+        }
+    }
+
+    /// The number of clients counted.
+    #[must_use]
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+
+    /// The number of clients counted with `value` for `dimension`.
+    #[must_use]
+    pub fn count(&self, dimension: Dimension, value: &str) -> u64 {
+        self.counts
+            .get(&dimension)
+            .and_then(|counts| counts.get(value))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// The percentage of all clients counted with `value` for `dimension`.
+    #[must_use]
+    pub fn share(&self, dimension: Dimension, value: &str) -> f64 {
+        percent(self.count(dimension, value), self.total)
+    }
+
+    /// The `n` most common values of `dimension`, most common first. Values
+    /// that are equally common are listed alphabetically.
+    #[must_use]
+    pub fn top(&self, dimension: Dimension, n: usize) -> Vec<Share> {
+        let Some(counts) = self.counts.get(&dimension) else {
+            return Vec::new();
+        };
+
+        let mut entries: Vec<_> = counts.iter().collect();
+        // The sort is stable, so ties stay in alphabetical order.
+        entries.sort_by_key(|&(_, &count)| Reverse(count));
+        entries
+            .into_iter()
+            .take(n)
+            .map(|(value, &count)| Share {
+                value: value.clone(),
+                count,
+                percent: percent(count, self.total),
+            })
+            .collect()
+    }
+
+    /// The `n` most common values of every dimension.
+    #[must_use]
+    pub fn report(&self, n: usize) -> Report {
+        Report {
+            total: self.total,
+            tables: Dimension::ALL
+                .iter()
+                .map(|&dimension| (dimension, self.top(dimension, n)))
+                .collect(),
+        }
+    }
+}
+
+impl<'a> Extend<Client<'a>> for UsageStats {
+    fn extend<I: IntoIterator<Item = Client<'a>>>(&mut self, clients: I) {
+        for client in clients {
+            self.add(&client);
+        }
+    }
+}
+
+impl<'a> FromIterator<Client<'a>> for UsageStats {
+    fn from_iter<I: IntoIterator<Item = Client<'a>>>(clients: I) -> Self {
+        let mut stats = UsageStats::default();
+        stats.extend(clients);
+        stats
+    }
+}
+
+#[allow(clippy::cast_precision_loss)]
+fn percent(count: u64, total: u64) -> f64 {
+    if total == 0 {
+        0.0
+    } else {
+        count as f64 * 100.0 / total as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Parser, UserAgentParser};
+
+    const REGEXES: &[u8] = br"
+user_agent_parsers:
+  - regex: '(Firefox)/(\d+)\.(\d+)'
+  - regex: '(Chrome)/(\d+)\.(\d+)'
+os_parsers:
+  - regex: '(Windows) NT (\d+)\.(\d+)'
+device_parsers:
+  - regex: '(iPhone)'
+    brand_replacement: 'Apple'
+bot_parsers:
+  - regex: '(Googlebot)'
+    category: search_engine
+";
+
+    const USER_AGENTS: &[&str] = &[
+        "Mozilla/5.0 (Windows NT 10.0) Firefox/115.0",
+        "Mozilla/5.0 (Windows NT 10.0) Chrome/114.0",
+        "Mozilla/5.0 (iPhone) Firefox/115.0",
+        "Googlebot/2.1",
+    ];
+
+    fn parser() -> UserAgentParser {
+        UserAgentParser::builder()
+            .with_bot(true)
+            .build_from_bytes(REGEXES)
+            .expect("Parser creation failed")
+    }
+
+    #[test]
+    fn counts_every_dimension() {
+        let parser = parser();
+        let stats: UsageStats = USER_AGENTS.iter().map(|ua| parser.parse(ua)).collect();
+
+        assert_eq!(stats.total(), 4);
+        assert_eq!(stats.count(Dimension::BrowserVersion, "Firefox 115"), 2);
+        assert_eq!(stats.count(Dimension::OsVersion, "Windows 10"), 2);
+        assert_eq!(stats.count(Dimension::DeviceBrand, "Apple"), 1);
+        assert_eq!(stats.count(Dimension::DeviceBrand, "Other"), 3);
+        assert_eq!(stats.count(Dimension::DeviceType, "mobile"), 1);
+        assert_eq!(stats.count(Dimension::Bot, "Googlebot"), 1);
+        assert!((stats.share(Dimension::Browser, "Chrome") - 25.0).abs() < f64::EPSILON);
+
+        let top = stats.top(Dimension::Browser, 2);
+        assert_eq!(
+            top.iter()
+                .map(|s| (s.value.as_str(), s.count))
+                .collect::<Vec<_>>(),
+            vec![("Firefox", 2), ("Chrome", 1)]
+        );
+        assert_eq!(stats.top(Dimension::Bot, 10).len(), 1);
+    }
+
+    #[test]
+    fn merges_across_threads() {
+        let parser = parser();
+        let expected: UsageStats =
+            USER_AGENTS.iter().map(|ua| parser.parse(ua)).collect();
+
+        let merged = std::thread::scope(|scope| {
+            let handles: Vec<_> = USER_AGENTS
+                .chunks(2)
+                .map(|chunk| {
+                    let parser = &parser;
+                    scope.spawn(move || {
+                        chunk
+                            .iter()
+                            .map(|ua| parser.parse(ua))
+                            .collect::<UsageStats>()
+                    })
+                })
+                .collect();
+            let mut merged = UsageStats::default();
+            for handle in handles {
+                merged.merge(handle.join().unwrap());
+            }
+            merged
+        });
+
+        assert_eq!(merged, expected);
+    }
+
+    #[test]
+    fn serializes_reports() {
+        let parser = parser();
+        let stats: UsageStats = USER_AGENTS.iter().map(|ua| parser.parse(ua)).collect();
+
+        let yaml = serde_yaml::to_string(&stats.report(1)).unwrap();
+        assert!(yaml.contains(
+            "  browser_version:\n  - value: Firefox 115\n    count: 2\n    percent: 50.0\n"
+        ));
+
+        let roundtrip: UsageStats =
+            serde_yaml::from_str(&serde_yaml::to_string(&stats).unwrap()).unwrap();
+        assert_eq!(roundtrip, stats);
+    }
+}
@@ -0,0 +1,213 @@
+use std::{borrow::Cow, cmp::Ordering, fmt};
+
+use super::{Deserialize, Serialize};
+
+/// Describes the `Major`, `Minor`, `Patch`, and `PatchMinor` parts of a
+/// `UserAgent` or `OS` version, ordered the way people read version numbers.
+///
+/// Parts are compared piece by piece, with runs of digits compared
+/// numerically, so `10` sorts after `9`. A part with a trailing pre-release
+/// marker sorts before the plain number, so `4.0b8pre` comes before `4.0`, and
+/// a missing part sorts before any present one, so `110` comes before `110.0`.
+///
+/// ```rust
+/// # use uaparser::*;
+/// assert!(Version::parse("4.0b8pre") < Version::parse("4.0"));
+/// assert!(Version::parse("110.0.5481") >= Version::parse("110"));
+/// assert_eq!(Version::parse("4.0.8").to_string(), "4.0.8");
+/// ```
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, Hash, PartialEq)]
+pub struct Version<'a> {
+    pub major: Cow<'a, str>,
+    pub minor: Option<Cow<'a, str>>,
+    pub patch: Option<Cow<'a, str>>,
+    pub patch_minor: Option<Cow<'a, str>>,
+}
+
+impl<'a> Version<'a> {
+    /// Splits a dotted version string into its parts. Anything after the
+    /// fourth part is kept as part of `patch_minor`.
+    #[must_use]
+    pub fn parse(version: &'a str) -> Self {
+        let mut parts = version.splitn(4, '.').map(Cow::Borrowed);
+        Version {
+            major: parts.next().unwrap_or_default(),
+            minor: parts.next(),
+            patch: parts.next(),
+            patch_minor: parts.next(),
+        }
+    }
+
+    /// Converts this into a `Version` that owns all of its data.
+    #[must_use]
+    pub fn into_owned(self) -> Version<'static> {
+        Version {
+            major: Cow::Owned(self.major.into_owned()),
+            minor: self.minor.map(|x| Cow::Owned(x.into_owned())),
+            patch: self.patch.map(|x| Cow::Owned(x.into_owned())),
+            patch_minor: self.patch_minor.map(|x| Cow::Owned(x.into_owned())),
+        }
+    }
+
+    fn parts(&self) -> [Option<&str>; 4] {
+        [
+            Some(&self.major),
+            self.minor.as_deref(),
+            self.patch.as_deref(),
+            self.patch_minor.as_deref(),
+        ]
+    }
+}
+
+impl Ord for Version<'_> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.parts()
+            .iter()
+            .zip(other.parts().iter())
+            .map(|parts| match parts {
+                (Some(a), Some(b)) => compare_part(a, b),
+                (a, b) => a.is_some().cmp(&b.is_some()),
+            })
+            .find(|ordering| ordering.is_ne())
+            .unwrap_or(Ordering::Equal)
+    }
+}
+
+impl PartialOrd for Version<'_> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for Version<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.major)?;
+        // A part after a missing one is meaningless on its own, so it is left
+        // out rather than shifted into the gap.
+        let parts = self.parts();
+        for part in parts[1..].iter().take_while(|x| x.is_some()).flatten() {
+            write!(f, ".{part}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Compares a single version part, such as `0b8pre`, run by run.
+fn compare_part(a: &str, b: &str) -> Ordering {
+    let mut a_runs = runs(a);
+    let mut b_runs = runs(b);
+
+    loop {
+        let ordering = match (a_runs.next(), b_runs.next()) {
+            (None, None) => return a.cmp(b),
+            // A trailing marker like `b8pre` makes this a pre-release of the
+            // plain number, while trailing digits make it a later version.
+            (None, Some(run)) if pre_release(run) => Ordering::Greater,
+            (None, Some(_)) => Ordering::Less,
+            (Some(run), None) if pre_release(run) => Ordering::Less,
+            (Some(_), None) => Ordering::Greater,
+            (Some(x), Some(y)) => compare_run(x, y),
+        };
+
+        if ordering.is_ne() {
+            return ordering;
+        }
+    }
+}
+
+fn compare_run(a: &str, b: &str) -> Ordering {
+    match (numeric(a), numeric(b)) {
+        (true, true) => {
+            let a = a.trim_start_matches('0');
+            let b = b.trim_start_matches('0');
+            a.len().cmp(&b.len()).then_with(|| a.cmp(b))
+        }
+        (true, false) => Ordering::Greater,
+        (false, true) => Ordering::Less,
+        (false, false) => a.cmp(b),
+    }
+}
+
+/// Splits a version part into alternating runs of digits and non-digits.
+fn runs(part: &str) -> impl Iterator<Item = &str> {
+    let mut rest = part;
+    std::iter::from_fn(move || {
+        let first = rest.chars().next()?;
+        let digits = first.is_ascii_digit();
+        let end = rest
+            .find(|c: char| c.is_ascii_digit() != digits)
+            .unwrap_or(rest.len());
+        let (run, tail) = rest.split_at(end);
+        rest = tail;
+        Some(run)
+    })
+}
+
+fn numeric(run: &str) -> bool {
+    run.starts_with(|c: char| c.is_ascii_digit())
+}
+
+/// A letter-led run after the number, as in `b8pre` or `rc1`.
+fn pre_release(run: &str) -> bool {
+    !numeric(run)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn orders_numerically() {
+        let mut versions = vec![
+            "10.0",
+            "9.1",
+            "4.0",
+            "4.0b8pre",
+            "4.0.1",
+            "110",
+            "110.0.5481.77",
+            "4.0b10",
+            "4.0b9",
+            "4",
+        ];
+        versions.sort_by(|a, b| Version::parse(a).cmp(&Version::parse(b)));
+
+        assert_eq!(
+            versions,
+            vec![
+                "4",
+                "4.0b8pre",
+                "4.0b9",
+                "4.0b10",
+                "4.0",
+                "4.0.1",
+                "9.1",
+                "10.0",
+                "110",
+                "110.0.5481.77",
+            ]
+        );
+    }
+
+    #[test]
+    fn ordering_agrees_with_equality() {
+        let a = Version::parse("1.01");
+        let b = Version::parse("1.1");
+        assert_ne!(a, b);
+        assert_ne!(a.cmp(&b), Ordering::Equal);
+        assert_eq!(a.cmp(&a.clone()), Ordering::Equal);
+    }
+
+    #[test]
+    fn displays_dotted() {
+        assert_eq!(Version::parse("4.0.8").to_string(), "4.0.8");
+        assert_eq!(Version::parse("10").to_string(), "10");
+        assert_eq!(Version::parse("1.2.3.4.5").to_string(), "1.2.3.4.5");
+
+        let gap = Version {
+            patch: Some(Cow::Borrowed("3")),
+            ..Version::parse("1")
+        };
+        assert_eq!(gap.to_string(), "1");
+    }
+}